@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+
+
+
+/// the kinds of marker a line can carry. when a line has several, the sign
+/// column shows the one listed first
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum AnnotationKind{
+    DiagnosticError,
+    DiagnosticWarning,
+    DiagnosticInfo,
+    DiagnosticHint,
+    Breakpoint,
+    Bookmark,
+    VcsAdded,
+    VcsModified,
+    /// lines were removed just below this one
+    VcsDeleted,
+}
+impl AnnotationKind{
+    /// the sign column character and the theme scope it is colored with
+    pub fn sign(&self) -> (char, &'static str){
+        match self{
+            AnnotationKind::DiagnosticError => ('E', "diagnostic.error"),
+            AnnotationKind::DiagnosticWarning => ('W', "diagnostic.warning"),
+            AnnotationKind::DiagnosticInfo => ('I', "diagnostic.info"),
+            AnnotationKind::DiagnosticHint => ('H', "diagnostic.hint"),
+            AnnotationKind::Breakpoint => ('●', "ui.sign.breakpoint"),
+            AnnotationKind::Bookmark => ('»', "ui.sign.bookmark"),
+            AnnotationKind::VcsAdded => ('│', "diff.added"),
+            AnnotationKind::VcsModified => ('│', "diff.modified"),
+            AnnotationKind::VcsDeleted => ('▁', "diff.deleted"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Annotation{
+    pub kind: AnnotationKind,
+    /// details shown when listing a line's annotations, like a diagnostic's text
+    pub message: Option<String>,
+}
+impl Annotation{
+    pub fn new(kind: AnnotationKind, message: Option<String>) -> Self{
+        Self{kind, message}
+    }
+}
+
+/// markers attached to document lines, grouped by the source that supplied
+/// them. each source, like diagnostics, version control or bookmarks, replaces
+/// its own set without touching the others
+#[derive(Default)]
+pub struct Annotations{
+    /// source name -> 0 based document line -> that line's annotations
+    sources: BTreeMap<String, BTreeMap<usize, Vec<Annotation>>>,
+}
+impl Annotations{
+    /// replaces every annotation from `source` with the given (line, annotation) pairs
+    pub fn set(&mut self, source: &str, annotations: Vec<(usize, Annotation)>){
+        let mut lines: BTreeMap<usize, Vec<Annotation>> = BTreeMap::new();
+        for (line, annotation) in annotations{
+            lines.entry(line).or_default().push(annotation);
+        }
+        if lines.is_empty(){
+            self.sources.remove(source);
+        }else{
+            self.sources.insert(source.to_string(), lines);
+        }
+    }
+
+    /// adds an annotation of this kind to the line if it has none, or removes it.
+    /// used for markers the user places, like bookmarks
+    pub fn toggle(&mut self, source: &str, line: usize, kind: AnnotationKind){
+        let lines = self.sources.entry(source.to_string()).or_default();
+        let annotations = lines.entry(line).or_default();
+        match annotations.iter().position(|annotation| annotation.kind == kind){
+            Some(index) => {annotations.remove(index);}
+            None => annotations.push(Annotation::new(kind, None))
+        }
+        if annotations.is_empty(){
+            lines.remove(&line);
+        }
+        if lines.is_empty(){
+            self.sources.remove(source);
+        }
+    }
+
+    /// moves every annotation from `source` to the line `map` gives for its
+    /// old line. lines merged by an edit keep one copy of each annotation
+    pub fn remap(&mut self, source: &str, map: impl Fn(usize) -> usize){
+        let Some(lines) = self.sources.remove(source) else{
+            return;
+        };
+        let mut remapped: BTreeMap<usize, Vec<Annotation>> = BTreeMap::new();
+        for (line, annotations) in lines{
+            let target = remapped.entry(map(line)).or_default();
+            for annotation in annotations{
+                if !target.contains(&annotation){
+                    target.push(annotation);
+                }
+            }
+        }
+        self.sources.insert(source.to_string(), remapped);
+    }
+
+    /// every annotation on a line, most important first
+    pub fn at(&self, line: usize) -> Vec<&Annotation>{
+        let mut annotations: Vec<&Annotation> = self.sources.values()
+            .filter_map(|lines| lines.get(&line))
+            .flatten()
+            .collect();
+        annotations.sort_by_key(|annotation| annotation.kind);
+
+        annotations
+    }
+
+    /// how many annotations of a kind there are, across every source and line
+    pub fn count(&self, kind: AnnotationKind) -> usize{
+        self.sources.values()
+            .flat_map(|lines| lines.values())
+            .flatten()
+            .filter(|annotation| annotation.kind == kind)
+            .count()
+    }
+
+    /// the sign shown for a line, if it has any annotations
+    pub fn sign(&self, line: usize) -> Option<(char, &'static str)>{
+        self.at(line).first().map(|annotation| annotation.kind.sign())
+    }
+}
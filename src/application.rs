@@ -1,17 +1,38 @@
 use ratatui::{backend::CrosstermBackend, Terminal};
-use crate::ui::UserInterface;
-use std::error::Error;
+use crate::ui::{LineNumberMode, UserInterface};
+use crate::keymap::{Keymap, KeyChord};
+use crate::modal::{ModalState, Motion, Operator};
+use crate::clipboard::Clipboard;
+use crate::config::{Config, Formatter};
+use crate::registers::Registers;
+use crate::macros::Macros;
+use crate::messages::MessageLevel;
+use crate::picker::FilePicker;
+use crate::annotations::{Annotation, AnnotationKind};
+use crate::git::{self, GitGutter};
+use crate::status;
+use crate::shell;
+use crate::theme::{self, ColorSupport, Theme};
+use crate::error::ClientError;
+use crate::crash;
+use crate::diff;
+use crate::format::{self, TextEdit};
+use std::collections::VecDeque;
 use std::net::TcpStream;
+use signal_hook::consts::SIGTSTP;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use crossterm::{
     cursor,
     terminal,
     execute,
     ExecutableCommand
 };
-use crossterm::event::{self, KeyCode, KeyEvent, KeyModifiers};
-use nlo_text_editor_server::{ServerAction, ServerResponse, MESSAGE_SIZE};
+use crossterm::event::{self, KeyCode, MouseButton, MouseEvent, MouseEventKind};
+use nlo_text_editor_server::{DiagnosticSeverity, ServerAction, ServerResponse, MESSAGE_SIZE};
 use std::io::{Read, Write};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
 
 
 
@@ -25,7 +46,24 @@ use std::io::{Read, Write};
         // BlinkingBar
         // SteadyBar
 const CURSOR_STYLE: cursor::SetCursorStyle = cursor::SetCursorStyle::SteadyBlock;
-const VIEW_SCROLL_AMOUNT: usize = 1;
+pub const VIEW_SCROLL_AMOUNT: usize = 1;
+const MOUSE_SCROLL_AMOUNT: usize = 3;
+/// the longest gap between two clicks on the same cell that still counts as a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// how long a key sequence must stay incomplete before its continuations are listed
+const KEY_HELP_DELAY: Duration = Duration::from_millis(500);
+/// how deeply macros may play other macros, so one that plays itself still ends
+const MACRO_DEPTH_LIMIT: usize = 16;
+/// how many actions one playback may perform, counting nested macros and
+/// repeats, so a large count or a macro that plays itself twice still ends soon
+const MACRO_ACTION_LIMIT: usize = 100_000;
+/// how long input must pause before line markers catch up with edits
+const MARKER_DELAY: Duration = Duration::from_millis(300);
+/// annotation sources, each replacing only its own markers
+const DIAGNOSTICS_SOURCE: &str = "diagnostics";
+const GIT_SOURCE: &str = "git";
+const BOOKMARKS_SOURCE: &str = "bookmarks";
+const BREAKPOINTS_SOURCE: &str = "breakpoints";
 
 
 
@@ -36,9 +74,23 @@ pub enum Mode{
     Command,
     FindReplace,
     Goto,
+    Normal,
+    Visual,
+    UndoHistory,
+    Popup,
+    FilePicker,
     //Utility(UtilityKind),
 }
 
+/// what the next typed character names a register for
+#[derive(Clone, Copy)]
+enum RegisterPrompt{
+    /// the following copy, cut or paste
+    Text,
+    RecordMacro,
+    PlayMacro,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum WarningKind{
     //OpenFileIsModified,
@@ -47,32 +99,55 @@ pub enum WarningKind{
     //FileOpenFailed,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ClientAction{
     Backspace,
+    BackspaceWord,
     CommandModeAccept,
     CommandModeBackspace,
+    CommandModeBackspaceWord,
     CommandModeDelete,
+    CommandModeDeleteWord,
     CommandModeExit,
     CommandModeInsertChar(char),
     CommandModeMoveCursorLeft,
     CommandModeMoveCursorLineEnd,
     CommandModeMoveCursorLineStart,
     CommandModeMoveCursorRight,
+    CommandModeMoveCursorWordEnd,
+    CommandModeMoveCursorWordStart,
+    Copy,
+    Cut,
     Delete,
+    DeleteWord,
     DisplayLineNumbers,
     DisplayStatusBar,
+    ExtendSelectionTo(usize, usize),
+    FilePickerModeAccept,
+    FilePickerModeBackspace,
+    FilePickerModeBackspaceWord,
+    FilePickerModeExit,
+    FilePickerModeInsertChar(char),
+    FilePickerModeSelectNext,
+    FilePickerModeSelectPrevious,
+    FilterThroughShellCommand(String),
     FindReplaceModeAccept,
     FindReplaceModeBackspace,
+    FindReplaceModeBackspaceWord,
     FindReplaceModeDelete,
+    FindReplaceModeDeleteWord,
     FindReplaceModeExit,
     FindReplaceModeInsertChar(char),
     FindReplaceModeMoveCursorLeft,
     FindReplaceModeMoveCursorLineEnd,
     FindReplaceModeMoveCursorLineStart,
     FindReplaceModeMoveCursorRight,
+    FindReplaceModeMoveCursorWordEnd,
+    FindReplaceModeMoveCursorWordStart,
     FindReplaceModeNextInstance,
     FindReplaceModePreviousInstance,
     FindReplaceModeSwitchUtilBarFocus,
+    FormatDocument,
     GotoModeAccept,
     GotoModeBackspace,
     GotoModeDelete,
@@ -84,6 +159,7 @@ pub enum ClientAction{
     GotoModeMoveCursorRight,
     InsertChar(char),
     InsertNewline,
+    InsertShellCommandOutput(String),
     InsertTab,
     MoveCursorDocumentEnd,
     MoveCursorDocumentStart,
@@ -94,21 +170,73 @@ pub enum ClientAction{
     MoveCursorPageDown,
     MoveCursorPageUp,
     MoveCursorRight,
+    MoveCursorTo(usize, usize),
     MoveCursorUp,
     MoveCursorWordEnd,
     MoveCursorWordStart,
+    NextHunk,
     NoOp,
+    NormalModeAppend,
+    NormalModeAppendLineEnd,
+    NormalModeCancel,
+    NormalModeCount(u32),
+    NormalModeDeleteChar,
+    NormalModeInsertLineStart,
+    NormalModeMotion(Motion),
+    NormalModeOpenLineBelow,
+    NormalModeOperator(Operator),
+    Paste,
+    PasteCycle,
+    PlayMacro,
+    PopupModeExit,
+    PopupModeScrollDown,
+    PopupModeScrollUp,
+    PreviewHunk,
+    PreviousHunk,
     Quit,
     QuitIgnoringChanges,
+    Redo,
+    ReloadKeymap,
+    ReplayMacro(char, usize),
     Resize(u16, u16),
+    RunShellCommand(String),
     Save,
     ScrollViewDown(usize),
     ScrollViewLeft(usize),
     ScrollViewRight(usize),
     ScrollViewUp(usize),
+    SelectRegister,
+    SelectWordAt(usize, usize),
+    SetLineNumberMode(LineNumberMode),
     SetModeCommand,
+    SetModeFilePicker,
     SetModeFindReplace,
     SetModeGoto,
+    SetModeInsert,
+    SetModeNormal,
+    SetModeUndoHistory,
+    SetModeVisual,
+    SetTheme(String),
+    ShowLineAnnotations,
+    ShowMessages,
+    ShowRegisters,
+    ShowThemes,
+    StartMacroRecording(char),
+    Suspend,
+    ToggleBookmark,
+    ToggleBreakpoint,
+    ToggleKeyHelp,
+    ToggleMacroRecording,
+    ToggleSoftWrap,
+    ToggleTrailingWhitespace,
+    ToggleVisibleWhitespace,
+    Undo,
+    UndoHistoryModeAccept,
+    UndoHistoryModeExit,
+    UndoHistoryModeSelectNext,
+    UndoHistoryModeSelectPrevious,
+    UseRegister(char),
+    UtilBarClick(u16, u16),
     WarningModeExit,
 }
 
@@ -121,29 +249,111 @@ pub struct Application{
     supports_keyboard_enhancement: bool,
     stream: TcpStream,
     ui: UserInterface,
+    keymap: Keymap,
+    /// keys typed so far of a sequence that has not resolved yet
+    pending_keys: Vec<KeyChord>,
+    /// when the last pending key was pressed
+    pending_keys_since: Instant,
+    /// count and operator typed so far in normal mode
+    modal: ModalState,
+    /// time and cell of the last left click, for double click detection
+    last_click: Option<(Instant, u16, u16)>,
+    clipboard: Clipboard,
+    config: Config,
+    registers: Registers,
+    /// registers are written out as they change. false when they are not kept
+    /// between sessions, or when the saved ones failed to load, so the file is
+    /// left for the user to repair instead of being overwritten
+    save_registers: bool,
+    register_prompt: Option<RegisterPrompt>,
+    pending_register: Option<char>,
+    macros: Macros,
+    /// false when the saved macros failed to load, so the file is left for the
+    /// user to repair instead of being overwritten
+    save_macros: bool,
+    /// how many macros are playing inside each other
+    macro_depth: usize,
+    /// actions the current playback may still perform, None once it ran out
+    macro_actions_left: Option<usize>,
+    /// None if the open file is not tracked by git
+    git_gutter: Option<GitGutter>,
+    /// the document text that bookmarks, breakpoints and diagnostics were
+    /// last placed against. None until the first update after opening
+    marked_text: Option<String>,
+    /// the document may have changed since line markers were last updated
+    markers_stale: bool,
 }
 impl Application{
-    pub fn new() -> Result<Self, Box<dyn Error>>{
-        let (mut terminal, supports_keyboard_enhancement) = setup_terminal()?;
-        let terminal_size = terminal.size()?;
+    pub fn new() -> Result<Self, ClientError>{
+        // load before touching the terminal, so a bad keymap error prints to a normal screen
+        let keymap = Keymap::load()?;
+        let config = Config::load()?;
+        // saved registers are a convenience, so a damaged file starts them empty rather than failing
+        let registers = if config.persist_registers{Registers::load()}else{Ok(Registers::default())};
+        let macros = Macros::load();
+        let theme = Theme::load(&config.theme, color_support(&config))?;
+        //TODO: if can't connect, spawn new nlo_text_editor_server and retry
+        let stream = TcpStream::connect("127.0.0.1:7878").map_err(ClientError::Connect)?;
 
-        let stream = match TcpStream::connect("127.0.0.1:7878"){
-            Ok(stream) => {stream}
-            //TODO: if can't connect, spawn new nlo_text_editor_server and retry
-            Err(e) => {
-                restore_terminal(&mut terminal, supports_keyboard_enhancement)?;
-                println!("Could not connect to tcp stream. Make sure you have started an nlo_server process. error: {}", e);
-                return Err(Box::new(e));
-            }
-        };
+        // a terminal left half set up is put back before the error is reported
+        let (terminal, supports_keyboard_enhancement) = setup_terminal().map_err(|error| {
+            crash::restore_terminal();
+            ClientError::Terminal(error)
+        })?;
+        let terminal_size = terminal.size().map_err(|error| {
+            crash::restore_terminal();
+            ClientError::Terminal(error)
+        })?;
+
+        let mut ui = UserInterface::new(terminal_size, theme);
+        ui.set_tab_width(config.tab_width);
+        ui.set_line_number_mode(config.line_numbers);
+        ui.set_display_sign_column(config.sign_column);
+        ui.set_show_whitespace(config.show_whitespace);
+        ui.set_highlight_trailing_whitespace(config.highlight_trailing_whitespace);
+        ui.set_status_bar_layout(config.status_bar.clone());
+        let save_registers = config.persist_registers && registers.is_ok();
+        let registers = registers.unwrap_or_else(|error| {
+            ui.messages_mut().push(
+                MessageLevel::Error,
+                format!("could not load saved registers: {error}. they will not be saved this session")
+            );
+            Registers::default()
+        });
+        let save_macros = macros.is_ok();
+        let macros = macros.unwrap_or_else(|error| {
+            ui.messages_mut().push(
+                MessageLevel::Error,
+                format!("could not load saved macros: {error}. they will not be saved this session")
+            );
+            Macros::default()
+        });
 
         Ok(Self{
             should_quit: false,
-            mode: Mode::Insert,
+            mode: keymap.base_mode(),
             host_terminal: terminal,
             supports_keyboard_enhancement,
             stream,
-            ui: UserInterface::new(terminal_size)
+            ui,
+            keymap,
+            pending_keys: Vec::new(),
+            pending_keys_since: Instant::now(),
+            modal: ModalState::default(),
+            last_click: None,
+            clipboard: Clipboard::detect(),
+            config,
+            registers,
+            save_registers,
+            register_prompt: None,
+            pending_register: None,
+            macros,
+            save_macros,
+            macro_depth: 0,
+            macro_actions_left: None,
+            git_gutter: None,
+            marked_text: None,
+            markers_stale: false,
         })
     }
 
@@ -153,10 +363,61 @@ impl Application{
     //Handler Execution: executes and performs the necessary actions based on the event
         //(For example, if a user presses "Ctrl+S", the corresponding handler might save the current buffer to disk)
     //Redraw and Display: Updates display (or redraws the screen) to reflect any changes resulting from the event handling
-    pub fn run(&mut self, file_path: String) -> Result<(), Box<dyn Error>>{
-        let path = PathBuf::from(file_path).canonicalize().expect("could not expand relative file path");
+    pub fn run(&mut self, file_path: String) -> Result<(), ClientError>{
+        let path = PathBuf::from(file_path);
+        let path = path.canonicalize().map_err(|error| ClientError::FilePath{path, error})?;
+        // with no document to show, there is nothing to go on with
+        self.open_file(path)?;
+        if self.config.soft_wrap{
+            self.set_soft_wrap(true)?;
+        }
+
+        loop{
+            self.ui.update_layouts(self.mode);
+            self.update_pending_keys();
+            self.update_key_help();
+            self.ui.render(&mut self.host_terminal, self.mode).map_err(ClientError::Terminal)?;
+            // an idle refresh inside handle_event can fail like an action can
+            let actions = self.handle_event();
+            let Some(actions) = self.report_error(actions)? else{
+                continue;
+            };
+            let mut actions = VecDeque::from(actions);
+            while let Some(action) = actions.pop_front(){
+                self.macros.record(&action);
+                let result = self.perform_client_action(action);
+                self.report_error(result)?;
+                if self.should_quit(){
+                    return Ok(());
+                }
+                // keys typed past this action's are read in the mode it left the editor in
+                if actions.is_empty(){
+                    actions.extend(self.next_pending_action());
+                }
+            }
+        }
+    }
+
+    /// opens a file in the server and shows it in place of the current
+    /// document. a refusal from the server is returned as ClientError::Server,
+    /// leaving the current document as it was
+    fn open_file(&mut self, path: PathBuf) -> Result<(), ClientError>{
         //OPEN FILE
-        let response = self.do_ipc_things(ServerAction::OpenFile{file_path: path})?;
+        match self.do_ipc_things(ServerAction::OpenFile{file_path: path.clone()})?{
+            ServerResponse::Failed(message) => return Err(ClientError::Server(message)),
+            response => self.process_server_response(response)
+        }
+        self.git_gutter = GitGutter::load(&path);
+        self.ui.set_git_branch(git::branch(&path));
+        // a new file has no line endings yet
+        self.ui.set_line_ending(std::fs::read(&path).ok().map(|bytes| status::line_ending(&bytes)));
+        self.ui.set_document_path(Some(path));
+        // markers placed in the previous document don't belong to this one
+        for source in [GIT_SOURCE, BOOKMARKS_SOURCE, BREAKPOINTS_SOURCE]{
+            self.ui.annotations_mut().set(source, Vec::new());
+        }
+        self.marked_text = None;
+        let response = self.do_ipc_things(ServerAction::RequestDiagnostics)?;
         self.process_server_response(response);
         
         self.ui.update_layouts(self.mode);
@@ -167,15 +428,26 @@ impl Application{
             }
         )?;
         self.process_server_response(response);
+        if self.ui.soft_wrap(){
+            self.set_soft_wrap(true)?;
+        }
+        // the document is open by now, so a failed first diff is no reason to give up on it
+        let result = self.update_markers();
+        self.report_error(result)?;
 
-        loop{
-            self.ui.update_layouts(self.mode);
-            self.ui.render(&mut self.host_terminal, self.mode)?;
-            let action = self.handle_event()?;
-            self.perform_client_action(action)?;
-            if self.should_quit(){
-                return Ok(());
+        Ok(())
+    }
+
+    /// passes fatal errors on. an error that only spoiled one exchange with the
+    /// server is shown in the message area instead, and None returned
+    fn report_error<T>(&mut self, result: Result<T, ClientError>) -> Result<Option<T>, ClientError>{
+        match result{
+            Ok(value) => Ok(Some(value)),
+            Err(error) if !error.is_fatal() => {
+                self.ui.messages_mut().push(MessageLevel::Error, error.to_string());
+                Ok(None)
             }
+            Err(error) => Err(error)
         }
     }
 
@@ -185,6 +457,230 @@ impl Application{
     pub fn set_mode(&mut self, mode: Mode){
         self.mode = mode
     }
+    /// whether an action can change the document's text, and so move line
+    /// markers. cursor moves, scrolls and mode changes never do
+    fn edits_document(&self, action: &ClientAction) -> bool{
+        match action{
+            // a motion only edits when it completes an operator, like dw
+            ClientAction::NormalModeMotion(_) => self.modal.operator().is_some(),
+            action => matches!(action,
+                ClientAction::Backspace
+                | ClientAction::BackspaceWord
+                | ClientAction::Cut
+                | ClientAction::Delete
+                | ClientAction::DeleteWord
+                | ClientAction::FilterThroughShellCommand(_)
+                | ClientAction::FormatDocument
+                | ClientAction::InsertChar(_)
+                | ClientAction::InsertNewline
+                | ClientAction::InsertShellCommandOutput(_)
+                | ClientAction::InsertTab
+                | ClientAction::NormalModeDeleteChar
+                | ClientAction::NormalModeOpenLineBelow
+                | ClientAction::NormalModeOperator(_)
+                | ClientAction::Paste
+                | ClientAction::PasteCycle
+                | ClientAction::Redo
+                | ClientAction::Save
+                | ClientAction::Undo
+                | ClientAction::UndoHistoryModeAccept
+            )
+        }
+    }
+    /// brings line markers up to date with the document. bookmarks,
+    /// breakpoints and diagnostics move with the lines they were placed on,
+    /// and the version control markers are diffed against HEAD again
+    fn update_markers(&mut self) -> Result<(), ClientError>{
+        self.markers_stale = false;
+        let Some(text) = self.document_text()? else{
+            return Ok(());
+        };
+        if let Some(marked_text) = self.marked_text.as_deref().filter(|marked_text| *marked_text != text){
+            let old: Vec<&str> = marked_text.lines().collect();
+            let new: Vec<&str> = text.lines().collect();
+            let hunks = diff::diff_lines(&old, &new);
+            for source in [BOOKMARKS_SOURCE, BREAKPOINTS_SOURCE, DIAGNOSTICS_SOURCE]{
+                self.ui.annotations_mut().remap(source, |line| format::line_after(&hunks, line));
+            }
+        }
+        if let Some(git_gutter) = &mut self.git_gutter{
+            if git_gutter.update(&text){
+                let annotations = git_gutter.annotations();
+                self.ui.annotations_mut().set(GIT_SOURCE, annotations);
+            }
+        }
+        self.marked_text = Some(text);
+
+        Ok(())
+    }
+
+    /// the whole document's text, or None if the server answered otherwise
+    fn document_text(&mut self) -> Result<Option<String>, ClientError>{
+        match self.do_ipc_things(ServerAction::RequestDocumentText)?{
+            ServerResponse::DocumentText(text) => Ok(Some(text)),
+            response => {
+                self.process_server_response(response);
+                Ok(None)
+            }
+        }
+    }
+
+    /// the selected text, or None if the server answered otherwise
+    fn selected_text(&mut self) -> Result<Option<String>, ClientError>{
+        match self.do_ipc_things(ServerAction::RequestSelectedText)?{
+            ServerResponse::SelectedText(text) => Ok(Some(text)),
+            response => {
+                self.process_server_response(response);
+                Ok(None)
+            }
+        }
+    }
+
+    /// runs a shell command, reporting its stderr and any failure in the
+    /// message area. returns its stdout only if it succeeded
+    fn run_shell_command(&mut self, command: &str, input: Option<&str>) -> Option<String>{
+        let output = match shell::run(command, input){
+            Ok(output) => output,
+            Err(error) => {
+                self.ui.messages_mut().push(MessageLevel::Error, format!("could not run {command}: {error}"));
+                return None;
+            }
+        };
+        let stderr = output.stderr.trim();
+        if !stderr.is_empty(){
+            // the util bar shows a single line
+            self.ui.messages_mut().push(MessageLevel::Warning, stderr.lines().collect::<Vec<_>>().join(" | "));
+        }
+        match output.failure(){
+            Some(failure) => {
+                self.ui.messages_mut().push(MessageLevel::Error, format!("{command} {failure}"));
+                None
+            }
+            None => Some(output.stdout)
+        }
+    }
+
+    /// the formatter configured for the document's language
+    fn formatter(&self) -> Option<Formatter>{
+        let language = status::language(self.ui.document_path()?)?;
+        self.config.formatters.get(language).cloned()
+    }
+
+    /// runs the document through a formatter and applies only the lines it
+    /// changed, so cursors elsewhere stay put and each change can be undone.
+    /// a failing formatter leaves the document as it was. if applying the
+    /// changes fails partway, how many were applied is reported
+    fn format_document(&mut self, formatter: &Formatter) -> Result<(), ClientError>{
+        let Some(text) = self.document_text()? else{
+            return Ok(());
+        };
+        let Some(formatted) = self.run_shell_command(&formatter.command, Some(&text)) else{
+            return Ok(());
+        };
+        let old: Vec<&str> = text.lines().collect();
+        let new: Vec<&str> = formatted.lines().collect();
+        let hunks = diff::diff_lines(&old, &new);
+        if hunks.is_empty(){
+            return Ok(());
+        }
+        let cursor = self.ui.document_cursor_position();
+        // lines are compared without their endings, and written back with the document's
+        let line_ending = match status::line_ending(text.as_bytes()){
+            "CRLF" => "\r\n",
+            "CR" => "\r",
+            _ => "\n"
+        };
+        let edits = format::minimal_edits(&old, &new, &hunks, line_ending);
+        let total = edits.len();
+        for (applied, edit) in edits.into_iter().enumerate(){
+            let stopped = match self.apply_edit(edit){
+                Ok(true) => None,
+                Ok(false) => Some(Ok(())),
+                Err(error) => Some(Err(error))
+            };
+            if let Some(result) = stopped{
+                let message = if applied == 0{
+                    "formatting failed before any change was applied".to_string()
+                }else{
+                    format!("formatting stopped after {applied} of {total} changes. undo to revert them")
+                };
+                self.ui.messages_mut().push(MessageLevel::Error, message);
+                return result;
+            }
+        }
+        // back to the cursor's line, wherever the edits above it moved it
+        if let Some(cursor) = cursor{
+            let y = format::line_after(&hunks, cursor.y()).min(new.len().saturating_sub(1));
+            let x = cursor.x().min(new.get(y).map(|line| line.graphemes(true).count()).unwrap_or(0));
+            let response = self.do_ipc_things(ServerAction::MoveCursorTo{x, y})?;
+            self.process_server_response(response);
+        }
+        self.ui.messages_mut().push(MessageLevel::Info, format!("formatted, {total} regions changed"));
+
+        Ok(())
+    }
+
+    /// replaces the text between an edit's positions. returns false if the
+    /// server refused a step, which it reports itself
+    fn apply_edit(&mut self, edit: TextEdit) -> Result<bool, ClientError>{
+        let (x, y) = edit.start;
+        let mut actions = vec![ServerAction::MoveCursorTo{x, y}];
+        if edit.start != edit.end{
+            let (x, y) = edit.end;
+            actions.push(ServerAction::ExtendSelectionTo{x, y});
+        }
+        actions.push(if edit.text.is_empty(){ServerAction::Delete}else{ServerAction::InsertText(edit.text)});
+        for action in actions{
+            let response = self.do_ipc_things(action)?;
+            let refused = matches!(response, ServerResponse::Failed(_));
+            self.process_server_response(response);
+            if refused{
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// moves the cursor to the first line of the next or previous git hunk
+    fn goto_hunk(&mut self, next: bool) -> Result<(), ClientError>{
+        let line = self.ui.document_cursor_position().map(|cursor| cursor.y()).unwrap_or(0);
+        let target = match &self.git_gutter{
+            Some(git_gutter) if next => git_gutter.next_hunk(line),
+            Some(git_gutter) => git_gutter.previous_hunk(line),
+            None => None
+        };
+        if let Some(line_number) = target{
+            let response = self.do_ipc_things(ServerAction::GoTo{line_number})?;
+            self.process_server_response(response);
+        }
+
+        Ok(())
+    }
+
+    /// adds or removes a user placed marker on the cursor line
+    fn toggle_annotation(&mut self, source: &str, kind: AnnotationKind) -> Result<(), ClientError>{
+        // the cursor line is in the current text, so older markers catch up first
+        if self.markers_stale{
+            self.update_markers()?;
+        }
+        if let Some(cursor) = self.ui.document_cursor_position(){
+            self.ui.annotations_mut().toggle(source, cursor.y(), kind);
+        }
+
+        Ok(())
+    }
+
+    /// switches soft wrapping. while wrapping, the server sends whole lines and
+    /// stops scrolling the view horizontally
+    fn set_soft_wrap(&mut self, soft_wrap: bool) -> Result<(), ClientError>{
+        self.ui.set_soft_wrap(soft_wrap);
+        self.ui.set_wrap_at_words(self.config.wrap_at_words);
+        let response = self.do_ipc_things(ServerAction::SetClientViewWrap{enabled: soft_wrap})?;
+        self.process_server_response(response);
+
+        Ok(())
+    }
     pub fn should_quit(&self) -> bool{
         self.should_quit
     }
@@ -192,127 +688,212 @@ impl Application{
         self.should_quit = should_quit
     }
 
-    pub fn handle_event(&mut self) -> Result<ClientAction, Box<dyn Error>>{
-        match event::read()?{
+    pub fn handle_event(&mut self) -> Result<Vec<ClientAction>, ClientError>{
+        // a partially typed key sequence resolves to its longest match if no key follows in time
+        if !self.pending_keys.is_empty(){
+            let elapsed = self.pending_keys_since.elapsed();
+            let timeout = self.keymap.timeout();
+            // wake up early to list the sequence's continuations if it is still incomplete
+            if elapsed < KEY_HELP_DELAY && KEY_HELP_DELAY < timeout{
+                if !event::poll(KEY_HELP_DELAY - elapsed).map_err(ClientError::Terminal)?{
+                    return Ok(Vec::new());
+                }
+            }
+            else if !event::poll(timeout.saturating_sub(elapsed)).map_err(ClientError::Terminal)?{
+                return Ok(self.keymap.flush(self.mode(), &mut self.pending_keys).into_iter().collect());
+            }
+        }
+        // line markers wait for a pause in typing before catching up with edits
+        else if self.markers_stale && !event::poll(MARKER_DELAY).map_err(ClientError::Terminal)?{
+            self.update_markers()?;
+            return Ok(Vec::new());
+        }
+        // redraw when the util bar message times out
+        else if let Some(time_left) = self.ui.messages().time_left(){
+            if !event::poll(time_left).map_err(ClientError::Terminal)?{
+                return Ok(Vec::new());
+            }
+        }
+
+        match event::read().map_err(ClientError::Terminal)?{
+            event::Event::Key(key_event) if self.register_prompt.is_some() => {
+                Ok(self.answer_register_prompt(key_event.code).into_iter().collect())
+            }
             event::Event::Key(key_event) => {
-                Ok(match (key_event, self.mode()){
-                    // Insert Mode
-                    //(KeyEvent{modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT, code, ..}, Mode::Insert) => {Action::}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Right,         ..}, Mode::Insert) => {ClientAction::MoveCursorWordEnd}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Left,          ..}, Mode::Insert) => {ClientAction::MoveCursorWordStart}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Home,          ..}, Mode::Insert) => {ClientAction::MoveCursorDocumentStart}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::End,           ..}, Mode::Insert) => {ClientAction::MoveCursorDocumentEnd}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q'),     ..}, Mode::Insert) => {ClientAction::Quit}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('s'),     ..}, Mode::Insert) => {ClientAction::Save}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('g'),     ..}, Mode::Insert) => {ClientAction::SetModeGoto}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('f'),     ..}, Mode::Insert) => {ClientAction::SetModeFindReplace}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('l'),     ..}, Mode::Insert) => {ClientAction::DisplayLineNumbers}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('k'),     ..}, Mode::Insert) => {ClientAction::DisplayStatusBar}
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('o'),     ..}, Mode::Insert) => {ClientAction::SetModeCommand}
-                    //(KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('t'),     ..}, Mode::Insert) => {ClientAction::OpenNewTerminalWindow}
-                    (KeyEvent{modifiers: KeyModifiers::SHIFT,   code: KeyCode::Char(c), ..}, Mode::Insert) => {ClientAction::InsertChar(c)}
-                    (KeyEvent{modifiers: KeyModifiers::ALT,     code: KeyCode::Down,          ..}, Mode::Insert) => {ClientAction::ScrollViewDown(VIEW_SCROLL_AMOUNT)}
-                    (KeyEvent{modifiers: KeyModifiers::ALT,     code: KeyCode::Left,          ..}, Mode::Insert) => {ClientAction::ScrollViewLeft(VIEW_SCROLL_AMOUNT)}
-                    (KeyEvent{modifiers: KeyModifiers::ALT,     code: KeyCode::Right,         ..}, Mode::Insert) => {ClientAction::ScrollViewRight(VIEW_SCROLL_AMOUNT)}
-                    (KeyEvent{modifiers: KeyModifiers::ALT,     code: KeyCode::Up,            ..}, Mode::Insert) => {ClientAction::ScrollViewUp(VIEW_SCROLL_AMOUNT)}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Tab,           ..}, Mode::Insert) => {ClientAction::InsertTab}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Enter,         ..}, Mode::Insert) => {ClientAction::InsertNewline}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Delete,        ..}, Mode::Insert) => {ClientAction::Delete}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Backspace,     ..}, Mode::Insert) => {ClientAction::Backspace}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Up,            ..}, Mode::Insert) => {ClientAction::MoveCursorUp}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Down,          ..}, Mode::Insert) => {ClientAction::MoveCursorDown}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Left,          ..}, Mode::Insert) => {ClientAction::MoveCursorLeft}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Right,         ..}, Mode::Insert) => {ClientAction::MoveCursorRight}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::PageUp,        ..}, Mode::Insert) => {ClientAction::MoveCursorPageUp}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::PageDown,      ..}, Mode::Insert) => {ClientAction::MoveCursorPageDown}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Home,          ..}, Mode::Insert) => {ClientAction::MoveCursorLineStart}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::End,           ..}, Mode::Insert) => {ClientAction::MoveCursorLineEnd}
-                    //(KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Esc,           ..}, Mode::Insert) => {ClientAction::CollapseSelectionCursor}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Char(c), ..}, Mode::Insert) => {ClientAction::InsertChar(c)}
-    
-                    // Warning Mode
-                    (KeyEvent{modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q'), ..}, Mode::Warning(_)) => {ClientAction::QuitIgnoringChanges}
-                    (KeyEvent{modifiers: KeyModifiers::NONE,    code: KeyCode::Esc,       ..}, Mode::Warning(_)) => {ClientAction::WarningModeExit}
-    
-                    // Goto Mode
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Esc,           ..}, Mode::Goto) => {ClientAction::GotoModeExit}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Enter,         ..}, Mode::Goto) => {ClientAction::GotoModeAccept}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Backspace,     ..}, Mode::Goto) => {ClientAction::GotoModeBackspace}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Delete,        ..}, Mode::Goto) => {ClientAction::GotoModeDelete}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Right,         ..}, Mode::Goto) => {ClientAction::GotoModeMoveCursorRight}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Left,          ..}, Mode::Goto) => {ClientAction::GotoModeMoveCursorLeft}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Home,          ..}, Mode::Goto) => {ClientAction::GotoModeMoveCursorLineStart}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::End,           ..}, Mode::Goto) => {ClientAction::GotoModeMoveCursorLineEnd}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c), ..}, Mode::Goto) => {ClientAction::GotoModeInsertChar(c)}
-                
-                    // FindReplace Mode
-                    (KeyEvent{modifiers: KeyModifiers::SHIFT, code: KeyCode::Char(c), ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeInsertChar(c)}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Esc,           ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeExit}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Tab,           ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeSwitchUtilBarFocus}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Up,            ..}, Mode::FindReplace) => {ClientAction::FindReplaceModePreviousInstance}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Down,          ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeNextInstance}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Backspace,     ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeBackspace}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Delete,        ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeDelete}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Right,         ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeMoveCursorRight}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Left,          ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeMoveCursorLeft}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Home,          ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeMoveCursorLineStart}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::End,           ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeMoveCursorLineEnd}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Enter,         ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeAccept}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c), ..}, Mode::FindReplace) => {ClientAction::FindReplaceModeInsertChar(c)}
-                
-                    // Command Mode
-                    (KeyEvent{modifiers: KeyModifiers::SHIFT, code: KeyCode::Char(c), ..}, Mode::Command) => {ClientAction::CommandModeInsertChar(c)}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Esc,           ..}, Mode::Command) => {ClientAction::CommandModeExit}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c), ..}, Mode::Command) => {ClientAction::CommandModeInsertChar(c)}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Enter,         ..}, Mode::Command) => {ClientAction::CommandModeAccept}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Backspace,     ..}, Mode::Command) => {ClientAction::CommandModeBackspace}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Delete,        ..}, Mode::Command) => {ClientAction::CommandModeDelete}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Right,         ..}, Mode::Command) => {ClientAction::CommandModeMoveCursorRight}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Left,          ..}, Mode::Command) => {ClientAction::CommandModeMoveCursorLeft}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Home,          ..}, Mode::Command) => {ClientAction::CommandModeMoveCursorLineStart}
-                    (KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::End,           ..}, Mode::Command) => {ClientAction::CommandModeMoveCursorLineEnd}
-    
-                    // unhandled keybinds
-                    _ => {ClientAction::NoOp}
-                })
+                let action = self.keymap.feed(self.mode(), &mut self.pending_keys, key_event);
+                self.pending_keys_since = Instant::now();
+                Ok(action.into_iter().collect())
             },
-            event::Event::Resize(x, y) => {Ok(ClientAction::Resize(x, y))}
-            _ => {Ok(ClientAction::NoOp)}
+            event::Event::Mouse(mouse_event) => {Ok(vec![self.mouse_action(mouse_event)])}
+            event::Event::Resize(x, y) => {Ok(vec![ClientAction::Resize(x, y)])}
+            _ => {Ok(vec![ClientAction::NoOp])}
         }
     }
 
-    pub fn perform_client_action(&mut self, action: ClientAction) -> Result<(), Box<dyn Error>>{
+    /// the action a key picks at an open register prompt, closing the prompt.
+    /// returned as an action, so a macro recording sees which register was picked
+    fn answer_register_prompt(&mut self, code: KeyCode) -> Option<ClientAction>{
+        match (code, self.register_prompt.take()?){
+            (KeyCode::Char(name), prompt) if Registers::is_valid_name(name) => {
+                Some(
+                    match prompt{
+                        RegisterPrompt::Text => ClientAction::UseRegister(name),
+                        RegisterPrompt::RecordMacro => ClientAction::StartMacroRecording(name),
+                        RegisterPrompt::PlayMacro => {
+                            let (_, count) = self.modal.take();
+                            ClientAction::ReplayMacro(name, count.unwrap_or(1))
+                        }
+                    }
+                )
+            }
+            _ => None
+        }
+    }
+
+    /// the next action of the keys still pending after an action, resolved in
+    /// the mode it left the editor in. a register prompt it opened takes the next key
+    fn next_pending_action(&mut self) -> Option<ClientAction>{
+        if self.register_prompt.is_none(){
+            return self.keymap.resolve(self.mode(), &mut self.pending_keys, false);
+        }
+        if self.pending_keys.is_empty(){
+            return None;
+        }
+        let chord = self.pending_keys.remove(0);
+        match self.answer_register_prompt(chord.code()){
+            Some(action) => Some(action),
+            // a key that isn't a register name just closes the prompt
+            None => self.next_pending_action()
+        }
+    }
+
+    /// translates a mouse event into document or util bar actions
+    fn mouse_action(&mut self, mouse_event: MouseEvent) -> ClientAction{
+        let (column, row) = (mouse_event.column, mouse_event.row);
+        let document_focused = matches!(self.mode, Mode::Insert | Mode::Normal | Mode::Visual);
+        match mouse_event.kind{
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double_click = matches!(
+                    self.last_click, 
+                    Some((time, last_column, last_row)) 
+                        if time.elapsed() <= DOUBLE_CLICK_INTERVAL && last_column == column && last_row == row
+                );
+                // a third click starts over rather than counting as another double click
+                self.last_click = if double_click{None}else{Some((Instant::now(), column, row))};
+
+                if !document_focused{
+                    return ClientAction::UtilBarClick(column, row);
+                }
+                match self.ui.document_position(column, row, false){
+                    Some((x, y)) if double_click => ClientAction::SelectWordAt(x, y),
+                    Some((x, y)) => ClientAction::MoveCursorTo(x, y),
+                    None => ClientAction::NoOp
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if document_focused => {
+                match self.ui.document_position(column, row, true){
+                    Some((x, y)) => ClientAction::ExtendSelectionTo(x, y),
+                    None => ClientAction::NoOp
+                }
+            }
+            MouseEventKind::ScrollDown if document_focused => ClientAction::ScrollViewDown(MOUSE_SCROLL_AMOUNT),
+            MouseEventKind::ScrollUp if document_focused => ClientAction::ScrollViewUp(MOUSE_SCROLL_AMOUNT),
+            _ => ClientAction::NoOp
+        }
+    }
+
+    /// shows any macro recording, register prompt or picked register, the pending
+    /// normal mode count and operator, then the keys of an incomplete sequence
+    fn update_pending_keys(&mut self){
+        let mut parts = Vec::new();
+        if let Some(name) = self.macros.recording(){
+            parts.push(format!("recording @{name}"));
+        }
+        let mut typed = match (self.register_prompt, self.pending_register){
+            (Some(RegisterPrompt::Text), _) => "\"".to_string(),
+            (Some(RegisterPrompt::RecordMacro), _) => "q".to_string(),
+            (Some(RegisterPrompt::PlayMacro), _) => "@".to_string(),
+            (None, Some(name)) => format!("\"{name}"),
+            (None, None) => String::new()
+        };
+        typed.push_str(&self.modal.pending_text());
+        if !typed.is_empty(){
+            parts.push(typed);
+        }
+        parts.extend(self.pending_keys.iter().map(|chord| chord.to_string()));
+        self.ui.set_pending_keys(parts.join(" "));
+    }
+
+    /// lists the bindings for the current mode when requested, or the ways to
+    /// finish a key sequence that has been pending for a while
+    fn update_key_help(&mut self){
+        let pending_for_a_while = !self.pending_keys.is_empty() && self.pending_keys_since.elapsed() >= KEY_HELP_DELAY;
+        if self.ui.display_key_help() || pending_for_a_while{
+            let entries = self.keymap.help_entries(self.mode, &self.pending_keys);
+            self.ui.set_key_help(Some(entries));
+        }else{
+            self.ui.set_key_help(None);
+        }
+    }
+
+    pub fn perform_client_action(&mut self, action: ClientAction) -> Result<(), ClientError>{
+        // paste cycling only continues straight after a paste
+        if !matches!(action, ClientAction::Paste | ClientAction::PasteCycle | ClientAction::NoOp){
+            self.registers.end_cycle();
+        }
+        if self.edits_document(&action){
+            self.markers_stale = true;
+        }
+
         match action{
             ClientAction::Backspace => {
                 let response = self.do_ipc_things(ServerAction::Backspace)?;
                 self.process_server_response(response);
             }
+            ClientAction::BackspaceWord => {
+                let response = self.do_ipc_things(ServerAction::BackspaceWord)?;
+                self.process_server_response(response);
+            }
             ClientAction::CommandModeAccept => {
-                //if parse_command(editor, ui.util_bar().text()).is_ok(){
-                    self.ui.util_bar_mut().clear();
-                    self.ui.util_bar_mut().set_offset(0);
-                    self.set_mode(Mode::Insert);
-                //}
-                //ui.scroll(editor);
-    
-                //TODO: send action request to server
+                match parse_command(self.ui.util_bar().text()){
+                    Some(action) => {
+                        self.ui.util_bar_mut().clear();
+                        self.ui.util_bar_mut().set_offset(0);
+                        self.set_mode(self.keymap.base_mode());
+                        self.perform_client_action(action)?;
+                    }
+                    None => self.ui.util_bar_mut().set_text_is_valid(false)
+                }
             }
             ClientAction::CommandModeBackspace => {
                 self.ui.util_bar_mut().backspace();
                 self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_mut().set_text_is_valid(true);
+            }
+            ClientAction::CommandModeBackspaceWord => {
+                self.ui.util_bar_mut().backspace_word();
+                self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_mut().set_text_is_valid(true);
             }
             ClientAction::CommandModeDelete => {
                 self.ui.util_bar_mut().delete();
                 self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_mut().set_text_is_valid(true);
+            }
+            ClientAction::CommandModeDeleteWord => {
+                self.ui.util_bar_mut().delete_word();
+                self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_mut().set_text_is_valid(true);
             }
             ClientAction::CommandModeExit => {
                 self.ui.util_bar_mut().clear();
                 self.ui.util_bar_mut().set_offset(0);
-                self.set_mode(Mode::Insert);
+                self.set_mode(self.keymap.base_mode());
             }
             ClientAction::CommandModeInsertChar(c) => {
                 self.ui.util_bar_mut().insert_char(c);
                 self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_mut().set_text_is_valid(true);
             }
             ClientAction::CommandModeMoveCursorLeft => {
                 self.ui.util_bar_mut().move_cursor_left();
@@ -330,10 +911,31 @@ impl Application{
                 self.ui.util_bar_mut().move_cursor_right();
                 self.ui.util_bar_mut().scroll();
             }
+            ClientAction::CommandModeMoveCursorWordEnd => {
+                self.ui.util_bar_mut().move_cursor_word_end();
+                self.ui.util_bar_mut().scroll();
+            }
+            ClientAction::CommandModeMoveCursorWordStart => {
+                self.ui.util_bar_mut().move_cursor_word_start();
+                self.ui.util_bar_mut().scroll();
+            }
+            ClientAction::Copy => {
+                self.copy_selection()?;
+            }
+            ClientAction::Cut => {
+                if self.copy_selection()?{
+                    let response = self.do_ipc_things(ServerAction::Delete)?;
+                    self.process_server_response(response);
+                }
+            }
             ClientAction::Delete => {
                 let response = self.do_ipc_things(ServerAction::Delete)?;
                 self.process_server_response(response);
             }
+            ClientAction::DeleteWord => {
+                let response = self.do_ipc_things(ServerAction::DeleteWord)?;
+                self.process_server_response(response);
+            }
             ClientAction::DisplayLineNumbers => {
                 self.ui.set_display_line_numbers(!self.ui.display_line_numbers());
                 
@@ -358,6 +960,85 @@ impl Application{
                 )?;
                 self.process_server_response(response);
             }
+            ClientAction::ExtendSelectionTo(x, y) => {
+                let response = self.do_ipc_things(ServerAction::ExtendSelectionTo{x, y})?;
+                self.process_server_response(response);
+            }
+            ClientAction::FilePickerModeAccept => {
+                let Some(path) = self.ui.file_picker().and_then(FilePicker::selected_path) else{
+                    return Ok(());
+                };
+                self.ui.set_file_picker(None);
+                self.set_mode(self.keymap.base_mode());
+                match self.open_file(path){
+                    Err(ClientError::Server(message)) => {
+                        self.ui.messages_mut().push(MessageLevel::Error, format!("could not open file: {message}"));
+                    }
+                    result => result?
+                }
+            }
+            ClientAction::FilePickerModeBackspace => {
+                if let Some(picker) = self.ui.file_picker_mut(){
+                    picker.backspace();
+                }
+            }
+            ClientAction::FilePickerModeBackspaceWord => {
+                if let Some(picker) = self.ui.file_picker_mut(){
+                    picker.backspace_word();
+                }
+            }
+            ClientAction::FilePickerModeExit => {
+                self.ui.set_file_picker(None);
+                self.set_mode(self.keymap.base_mode());
+            }
+            ClientAction::FilePickerModeInsertChar(c) => {
+                if let Some(picker) = self.ui.file_picker_mut(){
+                    picker.insert_char(c);
+                }
+            }
+            ClientAction::FilePickerModeSelectNext => {
+                if let Some(picker) = self.ui.file_picker_mut(){
+                    picker.select_next();
+                }
+            }
+            ClientAction::FilePickerModeSelectPrevious => {
+                if let Some(picker) = self.ui.file_picker_mut(){
+                    picker.select_previous();
+                }
+            }
+            ClientAction::FilterThroughShellCommand(command) => {
+                // the selection is filtered, or the whole document when nothing is selected
+                let Some(selected) = self.selected_text()? else{
+                    return Ok(());
+                };
+                let whole_document = selected.is_empty();
+                let input = if whole_document{
+                    match self.document_text()?{
+                        Some(text) => text,
+                        None => return Ok(())
+                    }
+                }else{
+                    selected
+                };
+                let Some(mut output) = self.run_shell_command(&command, Some(&input)) else{
+                    return Ok(());
+                };
+                // most commands end their output with a newline, which shouldn't grow text that had none
+                if !input.ends_with('\n') && output.ends_with('\n'){
+                    output.pop();
+                }
+                if output == input{
+                    return Ok(());
+                }
+                if whole_document{
+                    let response = self.do_ipc_things(ServerAction::MoveCursorDocumentStart)?;
+                    self.process_server_response(response);
+                    let response = self.do_ipc_things(ServerAction::ExtendSelectionDocumentEnd)?;
+                    self.process_server_response(response);
+                }
+                let response = self.do_ipc_things(ServerAction::InsertText(output))?;
+                self.process_server_response(response);
+            }
             ClientAction::FindReplaceModeAccept => {}
             ClientAction::FindReplaceModeBackspace => {
                 if self.ui.util_bar_alternate_focused(){
@@ -378,6 +1059,16 @@ impl Application{
                 //    }
                 //}
             }
+            ClientAction::FindReplaceModeBackspaceWord => {
+                if self.ui.util_bar_alternate_focused(){
+                    self.ui.util_bar_alternate_mut().backspace_word();
+                }else{
+                    self.ui.util_bar_mut().backspace_word();
+                }
+    
+                self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_alternate_mut().scroll();
+            }
             ClientAction::FindReplaceModeDelete => {
                 if self.ui.util_bar_alternate_focused(){
                     self.ui.util_bar_alternate_mut().delete();
@@ -397,13 +1088,23 @@ impl Application{
                 //    }
                 //}
             }
+            ClientAction::FindReplaceModeDeleteWord => {
+                if self.ui.util_bar_alternate_focused(){
+                    self.ui.util_bar_alternate_mut().delete_word();
+                }else{
+                    self.ui.util_bar_mut().delete_word();
+                }
+    
+                self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_alternate_mut().scroll();
+            }
             ClientAction::FindReplaceModeExit => {
                 self.ui.util_bar_mut().clear();
                 self.ui.util_bar_alternate_mut().clear();
                 self.ui.util_bar_mut().set_offset(0);
                 self.ui.util_bar_alternate_mut().set_offset(0);
                 self.ui.set_util_bar_alternate_focused(false);
-                self.set_mode(Mode::Insert);
+                self.set_mode(self.keymap.base_mode());
             }
             ClientAction::FindReplaceModeInsertChar(c) => {
                 if self.ui.util_bar_alternate_focused(){
@@ -446,6 +1147,26 @@ impl Application{
                 self.ui.util_bar_mut().scroll();
                 self.ui.util_bar_alternate_mut().scroll();
             }
+            ClientAction::FindReplaceModeMoveCursorWordEnd => {
+                if self.ui.util_bar_alternate_focused(){
+                    self.ui.util_bar_alternate_mut().move_cursor_word_end();
+                }else{
+                    self.ui.util_bar_mut().move_cursor_word_end();
+                }
+    
+                self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_alternate_mut().scroll();
+            }
+            ClientAction::FindReplaceModeMoveCursorWordStart => {
+                if self.ui.util_bar_alternate_focused(){
+                    self.ui.util_bar_alternate_mut().move_cursor_word_start();
+                }else{
+                    self.ui.util_bar_mut().move_cursor_word_start();
+                }
+    
+                self.ui.util_bar_mut().scroll();
+                self.ui.util_bar_alternate_mut().scroll();
+            }
             ClientAction::FindReplaceModeMoveCursorLineEnd => {
                 if self.ui.util_bar_alternate_focused(){
                     self.ui.util_bar_alternate_mut().move_cursor_end();
@@ -469,6 +1190,12 @@ impl Application{
             ClientAction::FindReplaceModeSwitchUtilBarFocus => {
                 self.ui.set_util_bar_alternate_focused(!self.ui.util_bar_alternate_focused());
             }
+            ClientAction::FormatDocument => {
+                match self.formatter(){
+                    Some(formatter) => self.format_document(&formatter)?,
+                    None => self.ui.messages_mut().push(MessageLevel::Warning, "no formatter configured for this language")
+                }
+            }
             ClientAction::GotoModeAccept => {
                 if let Ok(line_number) = self.ui.util_bar().text().parse::<usize>(){
                         if line_number.saturating_sub(1) < self.ui.document_length(){
@@ -479,7 +1206,7 @@ impl Application{
                             
                             self.ui.util_bar_mut().clear();
                             self.ui.util_bar_mut().set_offset(0);
-                            self.set_mode(Mode::Insert);
+                            self.set_mode(self.keymap.base_mode());
                         }
                 }
             }
@@ -532,7 +1259,7 @@ impl Application{
             ClientAction::GotoModeExit => {
                 self.ui.util_bar_mut().clear();
                 self.ui.util_bar_mut().set_offset(0);
-                self.set_mode(Mode::Insert);
+                self.set_mode(self.keymap.base_mode());
             }
             ClientAction::GotoModeInsertChar(c) => {
                 self.ui.util_bar_mut().insert_char(c);
@@ -581,6 +1308,14 @@ impl Application{
                 let response = self.do_ipc_things(ServerAction::InsertNewline)?;
                 self.process_server_response(response);
             }
+            ClientAction::InsertShellCommandOutput(command) => {
+                if let Some(output) = self.run_shell_command(&command, None){
+                    if !output.is_empty(){
+                        let response = self.do_ipc_things(ServerAction::InsertText(output))?;
+                        self.process_server_response(response);
+                    }
+                }
+            }
             ClientAction::InsertTab => {
                 let response = self.do_ipc_things(ServerAction::InsertTab)?;
                 self.process_server_response(response);
@@ -594,7 +1329,12 @@ impl Application{
                 self.process_server_response(response);
             }
             ClientAction::MoveCursorDown => {
-                let response = self.do_ipc_things(ServerAction::MoveCursorDown)?;
+                // soft wrapped lines are stepped through one row at a time
+                let server_action = match self.ui.soft_wrap().then(|| self.ui.visual_line_move(false)).flatten(){
+                    Some((x, y)) => ServerAction::MoveCursorTo{x, y},
+                    None => ServerAction::MoveCursorDown
+                };
+                let response = self.do_ipc_things(server_action)?;
                 self.process_server_response(response);
             }
             ClientAction::MoveCursorLeft => {
@@ -613,6 +1353,10 @@ impl Application{
                 let response = self.do_ipc_things(ServerAction::MoveCursorRight)?;
                 self.process_server_response(response);
             }
+            ClientAction::MoveCursorTo(x, y) => {
+                let response = self.do_ipc_things(ServerAction::MoveCursorTo{x, y})?;
+                self.process_server_response(response);
+            }
             ClientAction::MoveCursorPageUp => {
                 let response = self.do_ipc_things(ServerAction::MoveCursorPageUp)?;
                 self.process_server_response(response);
@@ -622,43 +1366,219 @@ impl Application{
                 self.process_server_response(response);
             }
             ClientAction::MoveCursorUp => {
-                let response = self.do_ipc_things(ServerAction::MoveCursorUp)?;
+                let server_action = match self.ui.soft_wrap().then(|| self.ui.visual_line_move(true)).flatten(){
+                    Some((x, y)) => ServerAction::MoveCursorTo{x, y},
+                    None => ServerAction::MoveCursorUp
+                };
+                let response = self.do_ipc_things(server_action)?;
+                self.process_server_response(response);
+            }
+            ClientAction::MoveCursorWordStart => {
+                let response = self.do_ipc_things(ServerAction::MoveCursorWordStart)?;
                 self.process_server_response(response);
             }
-            ClientAction::MoveCursorWordStart => {}
-            ClientAction::MoveCursorWordEnd => {}
+            ClientAction::MoveCursorWordEnd => {
+                let response = self.do_ipc_things(ServerAction::MoveCursorWordEnd)?;
+                self.process_server_response(response);
+            }
+            ClientAction::NextHunk => {self.goto_hunk(true)?}
             ClientAction::NoOp => {}
+            ClientAction::NormalModeAppend => {
+                let response = self.do_ipc_things(ServerAction::MoveCursorRight)?;
+                self.process_server_response(response);
+                self.set_mode(Mode::Insert);
+            }
+            ClientAction::NormalModeAppendLineEnd => {
+                let response = self.do_ipc_things(ServerAction::MoveCursorLineEnd)?;
+                self.process_server_response(response);
+                self.set_mode(Mode::Insert);
+            }
+            ClientAction::NormalModeCancel => {self.modal.clear()}
+            ClientAction::NormalModeCount(digit) => {
+                if digit == 0 && !self.modal.has_count(){
+                    self.perform_client_action(ClientAction::NormalModeMotion(Motion::LineStart))?;
+                }else{
+                    self.modal.push_digit(digit);
+                }
+            }
+            ClientAction::NormalModeDeleteChar => {
+                let (_, count) = self.modal.take();
+                for _ in 0..count.unwrap_or(1){
+                    let response = self.do_ipc_things(ServerAction::Delete)?;
+                    self.process_server_response(response);
+                }
+            }
+            ClientAction::NormalModeInsertLineStart => {
+                let response = self.do_ipc_things(ServerAction::MoveCursorLineStart)?;
+                self.process_server_response(response);
+                self.set_mode(Mode::Insert);
+            }
+            ClientAction::NormalModeMotion(motion) => {
+                let (operator, count) = self.modal.take();
+                let extend_selection = operator.is_some() || matches!(self.mode, Mode::Visual);
+                match (operator, count){
+                    // the cursor's line and count more, above or below it
+                    (Some(_), count) if motion.is_linewise() => {
+                        let count = count.unwrap_or(1);
+                        if motion == Motion::Up{
+                            for _ in 0..count{
+                                let response = self.do_ipc_things(ServerAction::MoveCursorUp)?;
+                                self.process_server_response(response);
+                            }
+                        }
+                        self.select_lines(count.saturating_add(1))?;
+                    }
+                    (_, Some(line_number)) if motion.count_is_line_number() && !extend_selection => {
+                        let response = self.do_ipc_things(
+                            ServerAction::GoTo{line_number: line_number.saturating_sub(1)}
+                        )?;
+                        self.process_server_response(response);
+                    }
+                    _ => {
+                        for _ in 0..count.unwrap_or(1){
+                            let response = self.do_ipc_things(motion.server_action(extend_selection))?;
+                            self.process_server_response(response);
+                        }
+                    }
+                }
+                if let Some(operator) = operator{
+                    self.apply_operator(operator)?;
+                }
+            }
+            ClientAction::NormalModeOpenLineBelow => {
+                let response = self.do_ipc_things(ServerAction::MoveCursorLineEnd)?;
+                self.process_server_response(response);
+                let response = self.do_ipc_things(ServerAction::InsertNewline)?;
+                self.process_server_response(response);
+                self.set_mode(Mode::Insert);
+            }
+            ClientAction::NormalModeOperator(operator) => {
+                if matches!(self.mode, Mode::Visual){
+                    // visual mode already has its selection, so operate right away
+                    self.modal.clear();
+                    self.apply_operator(operator)?;
+                }
+                else if self.modal.operator() == Some(operator){
+                    // a doubled operator, like dd, acts on whole lines
+                    let (_, count) = self.modal.take();
+                    self.select_lines(count.unwrap_or(1))?;
+                    self.apply_operator(operator)?;
+                }
+                else{
+                    self.modal.set_operator(operator);
+                }
+            }
+            ClientAction::Paste => {
+                let text = match self.pending_register.take(){
+                    Some(name) => self.registers.get(name).unwrap_or_default().to_string(),
+                    None => {
+                        let text = self.clipboard.paste();
+                        self.registers.start_cycle(&text);
+                        text
+                    }
+                };
+                if !text.is_empty(){
+                    let response = self.do_ipc_things(ServerAction::InsertText(text))?;
+                    self.process_server_response(response);
+                }
+            }
+            ClientAction::PasteCycle => {
+                // swap the text just pasted for the next older kill ring entry
+                if let Some(text) = self.registers.cycle().map(|text| text.to_string()){
+                    let response = self.do_ipc_things(ServerAction::Undo)?;
+                    self.process_server_response(response);
+                    let response = self.do_ipc_things(ServerAction::InsertText(text))?;
+                    self.process_server_response(response);
+                }
+            }
+            ClientAction::PlayMacro => {self.register_prompt = Some(RegisterPrompt::PlayMacro)}
+            ClientAction::PopupModeExit => {
+                self.ui.set_popup(None);
+                self.set_mode(self.keymap.base_mode());
+            }
+            ClientAction::PopupModeScrollDown => {self.ui.scroll_popup_down()}
+            ClientAction::PopupModeScrollUp => {self.ui.scroll_popup_up()}
+            ClientAction::PreviewHunk => {
+                let line = self.ui.document_cursor_position().map(|cursor| cursor.y()).unwrap_or(0);
+                let hunk = self.git_gutter.as_ref().and_then(|git_gutter| git_gutter.hunk_at(line));
+                if let Some(hunk) = hunk{
+                    let mut lines: Vec<String> = hunk.old_lines.iter().map(|line| format!("- {line}")).collect();
+                    if lines.is_empty(){
+                        lines.push("added lines, nothing was here at HEAD".to_string());
+                    }
+                    let title = format!("HEAD lines {}-{}", hunk.old_start + 1, hunk.old_start + hunk.old_lines.len().max(1));
+                    self.ui.set_popup(Some((title, lines)));
+                    self.set_mode(Mode::Popup);
+                }
+            }
+            ClientAction::PreviousHunk => {self.goto_hunk(false)?}
             ClientAction::Quit => {
                 if self.ui.document_modified(){
                     self.set_mode(Mode::Warning(WarningKind::FocusedFileIsModified));
                 }else{
                     self.set_should_quit(true);
-
-                    // send server a close action
-                    let server_action = ServerAction::CloseConnection;
-                    let serialized_server_action = ron::to_string(&server_action)?;
-                    match self.stream.write(serialized_server_action.as_bytes()){
-                        Ok(_bytes_written) => {
-                            //if bytes_written == 0{} else {}
-                        }
-                        Err(e) => {return Err(Box::new(e));}
-                    }
-                    self.stream.flush()?;
+                    self.close_connection()?;
                 }
             }
             ClientAction::QuitIgnoringChanges => {
                 self.set_should_quit(true);
-
-                // send server a close action
-                let server_action = ServerAction::CloseConnection;
-                let serialized_server_action = ron::to_string(&server_action)?;
-                match self.stream.write(serialized_server_action.as_bytes()){
-                    Ok(_bytes_written) => {
-                        //if bytes_written == 0{} else {}
+                self.close_connection()?;
+            }
+            ClientAction::Redo => {
+                let response = self.do_ipc_things(ServerAction::Redo)?;
+                self.process_server_response(response);
+            }
+            ClientAction::ReloadKeymap => {
+                match Keymap::load(){
+                    Ok(keymap) => {
+                        self.keymap = keymap;
+                        // switching profiles can leave the editor in a mode with no way out
+                        if !self.keymap.has_mode(self.mode){
+                            if matches!(self.mode, Mode::Visual){
+                                let response = self.do_ipc_things(ServerAction::CollapseSelection)?;
+                                self.process_server_response(response);
+                            }
+                            self.modal.clear();
+                            self.set_mode(self.keymap.base_mode());
+                        }
+                    }
+                    // the previous bindings stay in place
+                    Err(error) => self.ui.messages_mut().push(MessageLevel::Error, error.to_string())
+                }
+            }
+            ClientAction::ReplayMacro(name, count) => {
+                if self.macro_depth >= MACRO_DEPTH_LIMIT{
+                    self.ui.messages_mut().push(MessageLevel::Warning, format!("macros nested more than {MACRO_DEPTH_LIMIT} deep, stopping"));
+                    return Ok(());
+                }
+                let actions = self.macros.get(name).map(|actions| actions.to_vec()).unwrap_or_default();
+                self.pending_keys.clear();
+                if self.macro_depth == 0{
+                    self.macro_actions_left = Some(MACRO_ACTION_LIMIT);
+                }
+                self.macro_depth += 1;
+                // stops at the first failure, but always leaves through here so the depth count stays balanced
+                let mut result = Ok(());
+                'playback: for _ in 0..count{
+                    for action in actions.iter().cloned(){
+                        self.macro_actions_left = match self.macro_actions_left{
+                            Some(left) if left > 0 => Some(left - 1),
+                            _ => None
+                        };
+                        if self.macro_actions_left.is_none(){
+                            break 'playback;
+                        }
+                        result = self.perform_client_action(action);
+                        if result.is_err() || self.should_quit(){
+                            break 'playback;
+                        }
                     }
-                    Err(e) => {return Err(Box::new(e));}
                 }
-                self.stream.flush()?;
+                self.macro_depth -= 1;
+                if self.macro_depth == 0 && self.macro_actions_left.is_none(){
+                    self.ui.messages_mut().push(MessageLevel::Warning, format!("macro stopped after {MACRO_ACTION_LIMIT} actions"));
+                }
+                result?;
             }
             ClientAction::Resize(x, y) => {
                 self.ui.set_terminal_size(x, y);
@@ -673,8 +1593,35 @@ impl Application{
                 )?;
                 self.process_server_response(response);
             }
+            ClientAction::RunShellCommand(command) => {
+                if let Some(output) = self.run_shell_command(&command, None){
+                    if output.trim().is_empty(){
+                        self.ui.messages_mut().push(MessageLevel::Info, format!("{command} finished"));
+                    }else{
+                        self.ui.set_popup(Some((format!("!{command}"), output.lines().map(str::to_string).collect())));
+                        self.set_mode(Mode::Popup);
+                    }
+                }
+            }
             ClientAction::Save => {
+                // a formatter failure is reported, but doesn't stop the save
+                if let Some(formatter) = self.formatter().filter(|formatter| formatter.format_on_save){
+                    if let Err(error) = self.format_document(&formatter){
+                        self.ui.messages_mut().push(MessageLevel::Error, format!("could not format: {error}"));
+                    }
+                }
                 let response = self.do_ipc_things(ServerAction::Save)?;
+                let saved = !matches!(response, ServerResponse::Failed(_));
+                self.process_server_response(response);
+                if saved{
+                    self.ui.messages_mut().push(MessageLevel::Info, "saved");
+                }
+                if let Some(git_gutter) = &mut self.git_gutter{
+                    git_gutter.reload_head();
+                }
+                // fresh diagnostics are for the saved text, so the old ones mustn't be moved onto them later
+                self.update_markers()?;
+                let response = self.do_ipc_things(ServerAction::RequestDiagnostics)?;
                 self.process_server_response(response);
             }
             ClientAction::ScrollViewDown(amount) => {
@@ -684,24 +1631,239 @@ impl Application{
             ClientAction::ScrollViewLeft(amount) => {
                 let response = self.do_ipc_things(ServerAction::ScrollClientViewLeft{amount})?;
                 self.process_server_response(response);
+                self.ui.scrolled_horizontally(false, amount);
             }
             ClientAction::ScrollViewRight(amount) => {
                 let response = self.do_ipc_things(ServerAction::ScrollClientViewRight{amount})?;
                 self.process_server_response(response);
+                self.ui.scrolled_horizontally(true, amount);
             }
             ClientAction::ScrollViewUp(amount) => {
                 let response = self.do_ipc_things(ServerAction::ScrollClientViewUp{amount})?;
                 self.process_server_response(response);
             }
+            ClientAction::SelectRegister => {
+                self.register_prompt = Some(RegisterPrompt::Text);
+                self.pending_register = None;
+            }
+            ClientAction::SelectWordAt(x, y) => {
+                let response = self.do_ipc_things(ServerAction::SelectWordAt{x, y})?;
+                self.process_server_response(response);
+            }
+            ClientAction::SetLineNumberMode(line_number_mode) => {
+                self.ui.set_line_number_mode(line_number_mode);
+                self.ui.set_display_line_numbers(true);
+            }
             ClientAction::SetModeCommand => {self.set_mode(Mode::Command)}
+            ClientAction::SetModeFilePicker => {
+                // unsaved changes are saved first, rather than left in a document no longer shown
+                if self.ui.document_modified(){
+                    self.ui.messages_mut().push(MessageLevel::Warning, "save the document before opening another file");
+                    return Ok(());
+                }
+                match std::env::current_dir(){
+                    Ok(directory) => {
+                        self.ui.set_file_picker(Some(FilePicker::new(&directory)));
+                        self.set_mode(Mode::FilePicker);
+                    }
+                    Err(error) => {
+                        self.ui.messages_mut().push(MessageLevel::Error, format!("could not read the working directory: {error}"));
+                    }
+                }
+            }
             ClientAction::SetModeFindReplace => {self.set_mode(Mode::FindReplace)}
             ClientAction::SetModeGoto => {self.set_mode(Mode::Goto)}
-            ClientAction::WarningModeExit => {self.set_mode(Mode::Insert)}
+            ClientAction::SetModeInsert => {self.set_mode(Mode::Insert)}
+            ClientAction::SetModeNormal => {
+                if matches!(self.mode, Mode::Visual){
+                    let response = self.do_ipc_things(ServerAction::CollapseSelection)?;
+                    self.process_server_response(response);
+                }
+                self.modal.clear();
+                self.set_mode(Mode::Normal);
+            }
+            ClientAction::SetModeUndoHistory => {
+                let response = self.do_ipc_things(ServerAction::RequestUndoHistory)?;
+                // on any other answer, the history from the last request would be shown as if current
+                let received = matches!(response, ServerResponse::UndoHistory{..});
+                self.process_server_response(response);
+                if received{
+                    self.set_mode(Mode::UndoHistory);
+                }
+            }
+            ClientAction::SetModeVisual => {self.set_mode(Mode::Visual)}
+            ClientAction::SetTheme(name) => {
+                match Theme::load(&name, color_support(&self.config)){
+                    Ok(theme) => self.ui.set_theme(theme),
+                    // the current theme stays in place
+                    Err(error) => self.ui.messages_mut().push(MessageLevel::Error, error.to_string())
+                }
+            }
+            ClientAction::ShowLineAnnotations => {
+                if let Some(cursor) = self.ui.document_cursor_position(){
+                    let mut lines: Vec<String> = self.ui.annotations().at(cursor.y()).iter()
+                        .map(|annotation| match &annotation.message{
+                            Some(message) => format!("{:?}: {message}", annotation.kind),
+                            None => format!("{:?}", annotation.kind)
+                        })
+                        .collect();
+                    if lines.is_empty(){
+                        lines.push("no annotations on this line".to_string());
+                    }
+                    self.ui.set_popup(Some((format!("line {}", cursor.y() + 1), lines)));
+                    self.set_mode(Mode::Popup);
+                }
+            }
+            ClientAction::ShowMessages => {
+                self.ui.messages_mut().dismiss();
+                self.ui.set_popup(Some(("messages".to_string(), self.ui.messages().listing())));
+                self.set_mode(Mode::Popup);
+            }
+            ClientAction::ShowRegisters => {
+                self.ui.set_popup(Some(("registers".to_string(), self.registers.listing())));
+                self.set_mode(Mode::Popup);
+            }
+            ClientAction::ShowThemes => {
+                let themes = theme::available_themes().into_iter()
+                    .map(|name| if name == self.ui.theme().name(){format!("{name} (current)")}else{name})
+                    .collect();
+                self.ui.set_popup(Some(("themes".to_string(), themes)));
+                self.set_mode(Mode::Popup);
+            }
+            ClientAction::StartMacroRecording(name) => {self.macros.start_recording(name)}
+            ClientAction::Suspend => {self.suspend()?}
+            ClientAction::ToggleBookmark => {self.toggle_annotation(BOOKMARKS_SOURCE, AnnotationKind::Bookmark)?}
+            ClientAction::ToggleBreakpoint => {self.toggle_annotation(BREAKPOINTS_SOURCE, AnnotationKind::Breakpoint)?}
+            ClientAction::ToggleKeyHelp => {self.ui.set_display_key_help(!self.ui.display_key_help())}
+            ClientAction::ToggleMacroRecording => {
+                if self.macros.stop_recording(){
+                    // a recording that can't be saved still plays in this session
+                    if self.save_macros{
+                        if let Err(error) = self.macros.save(){
+                            self.ui.messages_mut().push(MessageLevel::Error, format!("could not save macros: {error}"));
+                        }
+                    }
+                }else{
+                    self.register_prompt = Some(RegisterPrompt::RecordMacro);
+                }
+            }
+            ClientAction::ToggleSoftWrap => {self.set_soft_wrap(!self.ui.soft_wrap())?}
+            ClientAction::ToggleTrailingWhitespace => {
+                self.ui.set_highlight_trailing_whitespace(!self.ui.highlight_trailing_whitespace())
+            }
+            ClientAction::ToggleVisibleWhitespace => {self.ui.set_show_whitespace(!self.ui.show_whitespace())}
+            ClientAction::Undo => {
+                let response = self.do_ipc_things(ServerAction::Undo)?;
+                self.process_server_response(response);
+            }
+            ClientAction::UndoHistoryModeAccept => {
+                // with no entries, there is nothing selected to go back to
+                if !self.ui.undo_history().is_empty(){
+                    let index = self.ui.undo_history().selected();
+                    let response = self.do_ipc_things(ServerAction::UndoTo{index})?;
+                    self.process_server_response(response);
+                }
+                self.set_mode(self.keymap.base_mode());
+            }
+            ClientAction::UndoHistoryModeExit => {self.set_mode(self.keymap.base_mode())}
+            ClientAction::UndoHistoryModeSelectNext => {self.ui.undo_history_mut().select_next()}
+            ClientAction::UndoHistoryModeSelectPrevious => {self.ui.undo_history_mut().select_previous()}
+            ClientAction::UseRegister(name) => {self.pending_register = Some(name)}
+            ClientAction::UtilBarClick(column, row) => {
+                if self.ui.click_util_bar(column, row, self.mode){
+                    self.ui.util_bar_mut().scroll();
+                    self.ui.util_bar_alternate_mut().scroll();
+                }
+            }
+            ClientAction::WarningModeExit => {self.set_mode(self.keymap.base_mode())}
         }
     
         Ok(())
     }
 
+    /// copies the selected text to the pending register, or to the clipboard and
+    /// kill ring. returns false if nothing was selected
+    fn copy_selection(&mut self) -> Result<bool, ClientError>{
+        match self.do_ipc_things(ServerAction::RequestSelectedText)?{
+            ServerResponse::SelectedText(text) if !text.is_empty() => {
+                match self.pending_register.take(){
+                    Some(name) => self.registers.set(name, &text),
+                    None => {
+                        self.registers.push_kill(&text);
+                        // the text is kept in process even if the system clipboard fails
+                        if let Err(error) = self.clipboard.copy(&text){
+                            self.ui.messages_mut().push(
+                                MessageLevel::Warning,
+                                format!("could not copy to the system clipboard: {error}. it can still be pasted here")
+                            );
+                        }
+                    }
+                }
+                // saved as they change, so a crash or a killed terminal keeps them too
+                if self.save_registers{
+                    if let Err(error) = self.registers.save(){
+                        self.ui.messages_mut().push(MessageLevel::Error, format!("could not save registers: {error}"));
+                    }
+                }
+                Ok(true)
+            }
+            ServerResponse::SelectedText(_) => Ok(false),
+            response => {
+                self.process_server_response(response);
+                Ok(false)
+            }
+        }
+    }
+
+    /// runs a normal mode operator over the current selection. like vi,
+    /// deleted and changed text is yanked first
+    /// selects `count` whole lines from the cursor's, including the line break
+    /// after the last, for an operator to act on. at the end of the document
+    /// there is no break, so the selection reaches the end of the last line
+    fn select_lines(&mut self, count: usize) -> Result<(), ClientError>{
+        let response = self.do_ipc_things(ServerAction::MoveCursorLineStart)?;
+        self.process_server_response(response);
+        let first = self.ui.document_cursor_position().map(|position| position.y());
+        for _ in 0..count{
+            let response = self.do_ipc_things(ServerAction::ExtendSelectionDown)?;
+            self.process_server_response(response);
+        }
+        let last = self.ui.document_cursor_position().map(|position| position.y());
+        if let (Some(first), Some(last)) = (first, last){
+            if last < first.saturating_add(count){
+                let response = self.do_ipc_things(ServerAction::ExtendSelectionLineEnd)?;
+                self.process_server_response(response);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_operator(&mut self, operator: Operator) -> Result<(), ClientError>{
+        match operator{
+            Operator::Delete => {
+                self.copy_selection()?;
+                let response = self.do_ipc_things(ServerAction::Delete)?;
+                self.process_server_response(response);
+                self.set_mode(Mode::Normal);
+            }
+            Operator::Change => {
+                self.copy_selection()?;
+                let response = self.do_ipc_things(ServerAction::Delete)?;
+                self.process_server_response(response);
+                self.set_mode(Mode::Insert);
+            }
+            Operator::Yank => {
+                self.copy_selection()?;
+                let response = self.do_ipc_things(ServerAction::CollapseSelection)?;
+                self.process_server_response(response);
+                self.set_mode(Mode::Normal);
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn process_server_response(&mut self, response: ServerResponse){
         match response{
             ServerResponse::FileOpened{file_name, document_length} => {
@@ -717,7 +1879,26 @@ impl Application{
                 self.ui.set_document_cursor_position(document_cursor_position);
                 self.ui.set_document_modified(modified);
             }
-            ServerResponse::Failed(_) => {}
+            ServerResponse::Failed(message) => {self.ui.messages_mut().push(MessageLevel::Error, message)}
+            ServerResponse::UndoHistory{entries, current} => {
+                self.ui.undo_history_mut().set_entries(entries, current);
+            }
+            ServerResponse::SelectedText(_) => {}
+            ServerResponse::DocumentText(_) => {}
+            ServerResponse::Diagnostics(diagnostics) => {
+                let annotations = diagnostics.into_iter()
+                    .map(|diagnostic| {
+                        let kind = match diagnostic.severity(){
+                            DiagnosticSeverity::Error => AnnotationKind::DiagnosticError,
+                            DiagnosticSeverity::Warning => AnnotationKind::DiagnosticWarning,
+                            DiagnosticSeverity::Info => AnnotationKind::DiagnosticInfo,
+                            DiagnosticSeverity::Hint => AnnotationKind::DiagnosticHint,
+                        };
+                        (diagnostic.line(), Annotation::new(kind, Some(diagnostic.message().to_string())))
+                    })
+                    .collect();
+                self.ui.annotations_mut().set(DIAGNOSTICS_SOURCE, annotations);
+            }
             ServerResponse::CursorPosition{client_cursor_positions, document_cursor_position} => {
                 self.ui.set_client_cursor_position(client_cursor_positions);
                 self.ui.set_document_cursor_position(document_cursor_position);
@@ -725,43 +1906,129 @@ impl Application{
         }
     }
 
-    pub fn do_ipc_things(&mut self, action: ServerAction) -> Result<ServerResponse, Box<dyn Error>>{
+    pub fn do_ipc_things(&mut self, action: ServerAction) -> Result<ServerResponse, ClientError>{
         let serialized_action = ron::to_string(&action)?;
-        match self.stream.write(serialized_action.as_bytes()){
-            Ok(_bytes_written) => {
-                //if bytes_written == 0{} else {}
-            }
-            Err(e) => {return Err(Box::new(e));}
-        }
-        self.stream.flush()?;
+        self.stream.write_all(serialized_action.as_bytes()).map_err(ClientError::Transport)?;
+        self.stream.flush().map_err(ClientError::Transport)?;
     
         let mut response_buffer = [0u8; MESSAGE_SIZE];
         match self.stream.read(&mut response_buffer){
+            // a read of nothing means the server hung up
+            Ok(0) => Err(ClientError::Transport(std::io::ErrorKind::UnexpectedEof.into())),
             Ok(size) => {
                 let my_string = String::from_utf8_lossy(&response_buffer[0..size]);
-                match ron::from_str(&my_string){
-                    Ok(response) => {Ok(response)},
-                    Err(e) => {Err(Box::new(e))}
-                }
+                Ok(ron::from_str(&my_string)?)
             }
             Err(e) => {
-                println!("An error occurred. {}", e);
-                self.stream.shutdown(std::net::Shutdown::Both).unwrap();
-                Err(Box::new(e))
+                // the connection is unusable either way, so a failed shutdown changes nothing
+                let _ = self.stream.shutdown(std::net::Shutdown::Both);
+                Err(ClientError::Transport(e))
             }
         }
     }
 
-    pub fn restore_terminal(&mut self) -> Result<(), Box<dyn Error>>{
+    /// gives the terminal back to the shell and stops the process, as Ctrl-z
+    /// would outside raw mode. once continued, the terminal is set up again
+    /// and the view resized, in case the terminal changed size meanwhile
+    fn suspend(&mut self) -> Result<(), ClientError>{
+        self.restore_terminal().map_err(ClientError::Terminal)?;
+        // with no handler installed, this stops the process until SIGCONT
+        signal_hook::low_level::raise(SIGTSTP).map_err(ClientError::Terminal)?;
+
+        let (terminal, supports_keyboard_enhancement) = setup_terminal().map_err(ClientError::Terminal)?;
+        // a fresh terminal has empty buffers, so the next render redraws every cell
+        self.host_terminal = terminal;
+        self.supports_keyboard_enhancement = supports_keyboard_enhancement;
+        let size = self.host_terminal.size().map_err(ClientError::Terminal)?;
+        self.perform_client_action(ClientAction::Resize(size.width, size.height))
+    }
+
+    /// tells the server this client is leaving. the server sends no response
+    fn close_connection(&mut self) -> Result<(), ClientError>{
+        let serialized_server_action = ron::to_string(&ServerAction::CloseConnection)?;
+        self.stream.write_all(serialized_server_action.as_bytes()).map_err(ClientError::Transport)?;
+        self.stream.flush().map_err(ClientError::Transport)
+    }
+
+    pub fn restore_terminal(&mut self) -> std::io::Result<()>{
         restore_terminal(&mut self.host_terminal, self.supports_keyboard_enhancement)
     }
 }
 
-fn setup_terminal() -> Result<(Terminal<CrosstermBackend<std::io::Stdout>>, bool), Box<dyn Error>>{
+/// maps command mode text to the action it requests
+fn parse_command(command: &str) -> Option<ClientAction>{
+    // shell commands take the rest of the line verbatim
+    let shell_command = |command: &str| Some(command.trim().to_string()).filter(|command| !command.is_empty());
+    if let Some(command) = command.trim_start().strip_prefix('!'){
+        return shell_command(command).map(ClientAction::RunShellCommand);
+    }
+    if let Some(command) = command.trim_start().strip_prefix('|'){
+        return shell_command(command).map(ClientAction::FilterThroughShellCommand);
+    }
+    if let Some(command) = command.trim_start().strip_prefix('r').and_then(|rest| rest.trim_start().strip_prefix('!')){
+        return shell_command(command).map(ClientAction::InsertShellCommandOutput);
+    }
+
+    let mut words = command.split_whitespace();
+    match (words.next()?, words.next(), words.next(), words.next()){
+        ("reload-keymap", None, None, None) => Some(ClientAction::ReloadKeymap),
+        ("undolist", None, None, None) => Some(ClientAction::SetModeUndoHistory),
+        ("registers", None, None, None) => Some(ClientAction::ShowRegisters),
+        ("soft-wrap", None, None, None) => Some(ClientAction::ToggleSoftWrap),
+        ("whitespace", None, None, None) => Some(ClientAction::ToggleVisibleWhitespace),
+        ("trailing-whitespace", None, None, None) => Some(ClientAction::ToggleTrailingWhitespace),
+        ("bookmark", None, None, None) => Some(ClientAction::ToggleBookmark),
+        ("breakpoint", None, None, None) => Some(ClientAction::ToggleBreakpoint),
+        ("annotations", None, None, None) => Some(ClientAction::ShowLineAnnotations),
+        ("next-hunk", None, None, None) => Some(ClientAction::NextHunk),
+        ("previous-hunk", None, None, None) => Some(ClientAction::PreviousHunk),
+        ("hunk", None, None, None) => Some(ClientAction::PreviewHunk),
+        ("themes", None, None, None) => Some(ClientAction::ShowThemes),
+        ("messages", None, None, None) => Some(ClientAction::ShowMessages),
+        ("suspend", None, None, None) => Some(ClientAction::Suspend),
+        ("format", None, None, None) => Some(ClientAction::FormatDocument),
+        ("open", None, None, None) => Some(ClientAction::SetModeFilePicker),
+        ("theme", Some(name), None, None) => Some(ClientAction::SetTheme(name.to_string())),
+        // line-numbers absolute|relative|hybrid|none
+        ("line-numbers", Some(mode), None, None) => {
+            let mode = match mode{
+                "absolute" => LineNumberMode::Absolute,
+                "relative" => LineNumberMode::Relative,
+                "hybrid" => LineNumberMode::Hybrid,
+                "none" => LineNumberMode::None,
+                _ => return None
+            };
+            Some(ClientAction::SetLineNumberMode(mode))
+        }
+        // play <register> [count]
+        ("play", Some(name), count, None) => {
+            let mut chars = name.chars();
+            let name = match (chars.next(), chars.next()){
+                (Some(name), None) if Registers::is_valid_name(name) => name,
+                _ => return None
+            };
+            let count = match count{
+                Some(count) => count.parse().ok().filter(|count| *count > 0)?,
+                None => 1
+            };
+            Some(ClientAction::ReplayMacro(name, count))
+        }
+        _ => None
+    }
+}
+
+/// the configured color support, or what the terminal appears to have
+fn color_support(config: &Config) -> ColorSupport{
+    config.color_support.unwrap_or_else(ColorSupport::detect)
+}
+
+fn setup_terminal() -> std::io::Result<(Terminal<CrosstermBackend<std::io::Stdout>>, bool)>{
     let mut stdout = std::io::stdout();
     terminal::enable_raw_mode()?;
+    crash::terminal_taken(false);
     stdout.execute(crossterm::terminal::EnterAlternateScreen)?;
     stdout.execute(CURSOR_STYLE)?;
+    stdout.execute(event::EnableMouseCapture)?;
     
     let supports_keyboard_enhancement = terminal::supports_keyboard_enhancement().unwrap_or(false);
 
@@ -782,6 +2049,7 @@ fn setup_terminal() -> Result<(Terminal<CrosstermBackend<std::io::Stdout>>, bool
                 //| KeyboardEnhancementFlags::REPORT_EVENT_TYPES
             )
         )?;
+        crash::terminal_taken(true);
     }
 
     let terminal = Terminal::new(
@@ -794,14 +2062,16 @@ fn setup_terminal() -> Result<(Terminal<CrosstermBackend<std::io::Stdout>>, bool
 pub fn restore_terminal(
     terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, 
     supports_keyboard_enhancement: bool
-) -> Result<(), Box<dyn Error>>{
+) -> std::io::Result<()>{
     if supports_keyboard_enhancement{
         terminal.backend_mut().execute(event::PopKeyboardEnhancementFlags)?;
     }
     terminal::disable_raw_mode()?;
+    terminal.backend_mut().execute(event::DisableMouseCapture)?;
     terminal.backend_mut().execute(crossterm::terminal::LeaveAlternateScreen)?;
     terminal.backend_mut().execute(crossterm::cursor::SetCursorStyle::DefaultUserShape)?;
     terminal.show_cursor()?;
+    crash::terminal_released();
     
     Ok(())
 }
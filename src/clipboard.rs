@@ -0,0 +1,270 @@
+use std::error::Error;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+
+
+/// somewhere copied text can be stored and read back from
+pub trait ClipboardProvider{
+    fn set_contents(&mut self, text: &str) -> Result<(), Box<dyn Error>>;
+    fn contents(&mut self) -> Result<String, Box<dyn Error>>;
+}
+
+/// keeps copied text inside the editor process. used when no system clipboard is reachable
+#[derive(Default)]
+pub struct InProcess{
+    text: String,
+}
+impl ClipboardProvider for InProcess{
+    fn set_contents(&mut self, text: &str) -> Result<(), Box<dyn Error>>{
+        self.text = text.to_string();
+        Ok(())
+    }
+    fn contents(&mut self) -> Result<String, Box<dyn Error>>{
+        Ok(self.text.clone())
+    }
+}
+
+/// copies through the OSC 52 terminal escape sequence, which reaches the local
+/// clipboard over ssh and through tmux. few terminals allow reading it back,
+/// so pastes return the last text copied from this editor
+#[derive(Default)]
+pub struct Osc52{
+    last_copied: String,
+}
+impl ClipboardProvider for Osc52{
+    fn set_contents(&mut self, text: &str) -> Result<(), Box<dyn Error>>{
+        let sequence = format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+        // tmux only forwards escape sequences wrapped in its passthrough
+        let sequence = if std::env::var_os("TMUX").is_some(){
+            format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+        }else{
+            sequence
+        };
+        let mut stdout = std::io::stdout();
+        stdout.write_all(sequence.as_bytes())?;
+        stdout.flush()?;
+        self.last_copied = text.to_string();
+        Ok(())
+    }
+    fn contents(&mut self) -> Result<String, Box<dyn Error>>{
+        Ok(self.last_copied.clone())
+    }
+}
+
+/// copies and pastes by running external programs, like wl-copy/wl-paste or xclip
+pub struct External{
+    copy: (&'static str, &'static [&'static str]),
+    paste: (&'static str, &'static [&'static str]),
+}
+impl External{
+    pub fn wayland() -> Self{
+        Self{
+            copy: ("wl-copy", &[]),
+            paste: ("wl-paste", &["--no-newline"]),
+        }
+    }
+
+    pub fn xclip() -> Self{
+        Self{
+            copy: ("xclip", &["-selection", "clipboard"]),
+            paste: ("xclip", &["-selection", "clipboard", "-o"]),
+        }
+    }
+}
+impl ClipboardProvider for External{
+    fn set_contents(&mut self, text: &str) -> Result<(), Box<dyn Error>>{
+        let (program, args) = self.copy;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take(){
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success(){
+            return Err(format!("{program} exited with {status}").into());
+        }
+        Ok(())
+    }
+    fn contents(&mut self) -> Result<String, Box<dyn Error>>{
+        let (program, args) = self.paste;
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        if !output.status.success(){
+            return Err(format!("{program} exited with {}", output.status).into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// stores the clipboard in a plain file, so scripts driving the editor can
+/// seed what gets pasted and check what was copied
+pub struct Mock{
+    path: PathBuf,
+}
+impl Mock{
+    pub fn new(path: PathBuf) -> Self{
+        Self{path}
+    }
+}
+impl ClipboardProvider for Mock{
+    fn set_contents(&mut self, text: &str) -> Result<(), Box<dyn Error>>{
+        std::fs::write(&self.path, text)?;
+        Ok(())
+    }
+    fn contents(&mut self) -> Result<String, Box<dyn Error>>{
+        let mut text = String::new();
+        if self.path.exists(){
+            std::fs::File::open(&self.path)?.read_to_string(&mut text)?;
+        }
+        Ok(text)
+    }
+}
+
+/// the clipboard used by copy, cut and paste. text is always kept in process
+/// too, so a failing system clipboard still leaves something to paste
+pub struct Clipboard{
+    provider: Box<dyn ClipboardProvider>,
+    fallback: InProcess,
+}
+impl Clipboard{
+    pub fn new(provider: Box<dyn ClipboardProvider>) -> Self{
+        Self{provider, fallback: InProcess::default()}
+    }
+
+    /// picks a provider for the current environment. NLO_CLIPBOARD can force
+    /// one of osc52, wl-clipboard, xclip, in-process, or mock:<file path>
+    pub fn detect() -> Self{
+        let forced = std::env::var("NLO_CLIPBOARD").unwrap_or_default();
+        let provider: Box<dyn ClipboardProvider> = match forced.as_str(){
+            "osc52" => Box::new(Osc52::default()),
+            "wl-clipboard" => Box::new(External::wayland()),
+            "xclip" => Box::new(External::xclip()),
+            "in-process" => Box::new(InProcess::default()),
+            mock if mock.starts_with("mock:") => Box::new(Mock::new(PathBuf::from(&mock["mock:".len()..]))),
+            _ => {
+                if std::env::var_os("WAYLAND_DISPLAY").is_some() && program_exists("wl-copy") && program_exists("wl-paste"){
+                    Box::new(External::wayland())
+                }
+                else if std::env::var_os("DISPLAY").is_some() && program_exists("xclip"){
+                    Box::new(External::xclip())
+                }
+                else if std::env::var_os("SSH_TTY").is_some() || std::env::var_os("TMUX").is_some() || std::env::var_os("TERM").is_some(){
+                    Box::new(Osc52::default())
+                }
+                else{
+                    Box::new(InProcess::default())
+                }
+            }
+        };
+
+        Self::new(provider)
+    }
+
+    pub fn copy(&mut self, text: &str) -> Result<(), Box<dyn Error>>{
+        self.fallback.set_contents(text)?;
+        self.provider.set_contents(text)
+    }
+
+    /// the provider's contents, or the last text copied here if the provider fails
+    pub fn paste(&mut self) -> String{
+        match self.provider.contents(){
+            Ok(text) => text,
+            Err(_) => self.fallback.text.clone()
+        }
+    }
+}
+
+fn program_exists(program: &str) -> bool{
+    match std::env::var_os("PATH"){
+        Some(paths) => std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()),
+        None => false
+    }
+}
+
+fn base64_encode(bytes: &[u8]) -> String{
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3){
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        encoded.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        encoded.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        encoded.push(if chunk.len() > 1{ALPHABET[(n >> 6) as usize & 63] as char}else{'='});
+        encoded.push(if chunk.len() > 2{ALPHABET[n as usize & 63] as char}else{'='});
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    /// a file path under the temporary directory, unique to this process and test
+    fn temp_path(name: &str) -> PathBuf{
+        std::env::temp_dir().join(format!("nlo_clipboard_{}_{name}", std::process::id()))
+    }
+
+    struct Failing;
+    impl ClipboardProvider for Failing{
+        fn set_contents(&mut self, _text: &str) -> Result<(), Box<dyn Error>>{
+            Err("unreachable clipboard".into())
+        }
+        fn contents(&mut self) -> Result<String, Box<dyn Error>>{
+            Err("unreachable clipboard".into())
+        }
+    }
+
+    #[test]
+    fn base64_pads_partial_chunks(){
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xff, 0xfe, 0x00]), "//4A");
+        assert_eq!(base64_encode("é\n".as_bytes()), "w6kK");
+    }
+
+    #[test]
+    fn mock_reads_back_what_was_written(){
+        let path = temp_path("mock");
+        let mut mock = Mock::new(path.clone());
+        assert_eq!(mock.contents().unwrap(), "", "a missing file is an empty clipboard");
+        mock.set_contents("line\nnext").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line\nnext");
+        std::fs::write(&path, "seeded").unwrap();
+        assert_eq!(mock.contents().unwrap(), "seeded");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn paste_falls_back_to_the_last_copy(){
+        let mut clipboard = Clipboard::new(Box::new(Failing));
+        assert!(clipboard.copy("kept").is_err());
+        assert_eq!(clipboard.paste(), "kept");
+    }
+
+    #[test]
+    fn detect_honors_a_forced_provider(){
+        let path = temp_path("detect");
+        // the only test that touches the environment, so it can't race another
+        std::env::set_var("NLO_CLIPBOARD", format!("mock:{}", path.display()));
+        let mut clipboard = Clipboard::detect();
+        std::env::remove_var("NLO_CLIPBOARD");
+        clipboard.copy("copied").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "copied");
+        std::fs::write(&path, "from a script").unwrap();
+        assert_eq!(clipboard.paste(), "from a script");
+        std::fs::remove_file(path).unwrap();
+    }
+}
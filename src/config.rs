@@ -0,0 +1,120 @@
+use crate::display;
+use crate::status::StatusBarLayout;
+use crate::theme::{self, ColorSupport};
+use crate::ui::LineNumberMode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+
+
+const CONFIG_FILE_NAME: &str = "config.ron";
+
+
+
+#[derive(Debug)]
+pub enum ConfigError{
+    Io{path: PathBuf, error: std::io::Error},
+    Parse{path: PathBuf, error: ron::error::SpannedError},
+}
+impl fmt::Display for ConfigError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            ConfigError::Io{path, error} => {
+                write!(f, "could not read config {}: {}", path.display(), error)
+            }
+            ConfigError::Parse{path, error} => {
+                write!(f, "could not parse config {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+impl Error for ConfigError{}
+
+/// an external command that reads a document on stdin and writes it back formatted
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Formatter{
+    /// run through the user's shell, e.g. "rustfmt --emit stdout"
+    pub command: String,
+    /// format before every save, not only when asked to
+    #[serde(default)]
+    pub format_on_save: bool,
+}
+
+/// editor options read from the user's config file. every field is optional in the file
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config{
+    /// keep named registers and the kill ring between sessions
+    pub persist_registers: bool,
+    /// start with long lines wrapped instead of scrolled
+    pub soft_wrap: bool,
+    /// soft wrap after whitespace rather than at the last cell that fits
+    pub wrap_at_words: bool,
+    /// cells between tab stops
+    pub tab_width: usize,
+    /// start with markers drawn for whitespace, and indentation guides
+    pub show_whitespace: bool,
+    pub highlight_trailing_whitespace: bool,
+    pub line_numbers: LineNumberMode,
+    /// keep a column between line numbers and text for bookmark, diagnostic and version control signs
+    pub sign_column: bool,
+    /// a built in theme, "dark" or "light", or the name of a file in the themes directory
+    pub theme: String,
+    /// colors the terminal can show. detected from COLORTERM and TERM when left out
+    pub color_support: Option<ColorSupport>,
+    pub status_bar: StatusBarLayout,
+    /// formatters by language name, as shown by the status bar's language segment
+    pub formatters: HashMap<String, Formatter>,
+}
+impl Default for Config{
+    fn default() -> Self{
+        Self{
+            persist_registers: false,
+            soft_wrap: false,
+            wrap_at_words: false,
+            tab_width: display::DEFAULT_TAB_WIDTH,
+            show_whitespace: false,
+            highlight_trailing_whitespace: false,
+            line_numbers: LineNumberMode::Absolute,
+            sign_column: true,
+            theme: theme::DEFAULT_THEME.to_string(),
+            color_support: None,
+            status_bar: StatusBarLayout::default(),
+            formatters: HashMap::new(),
+        }
+    }
+}
+impl Config{
+    /// reads the user's config file, or the defaults if there is none
+    pub fn load() -> Result<Self, ConfigError>{
+        match config_dir().map(|dir| dir.join(CONFIG_FILE_NAME)){
+            Some(path) if path.exists() => {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|error| ConfigError::Io{path: path.clone(), error})?;
+                ron::from_str(&text)
+                    .map_err(|error| ConfigError::Parse{path, error})
+            }
+            _ => Ok(Self::default())
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/nlo` or `~/.config/nlo`
+pub fn config_dir() -> Option<PathBuf>{
+    match std::env::var_os("XDG_CONFIG_HOME"){
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("nlo")),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("nlo"))
+    }
+}
+
+/// `$XDG_DATA_HOME/nlo` or `~/.local/share/nlo`, for state kept between sessions
+pub fn data_dir() -> Option<PathBuf>{
+    match std::env::var_os("XDG_DATA_HOME"){
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("nlo")),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share").join("nlo"))
+    }
+}
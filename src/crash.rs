@@ -0,0 +1,97 @@
+use crate::config;
+use crossterm::{cursor, event, terminal, ExecutableCommand};
+use signal_hook::consts::{SIGHUP, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::backtrace::Backtrace;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+
+/// the terminal is in raw mode inside the alternate screen
+static TERMINAL_TAKEN: AtomicBool = AtomicBool::new(false);
+/// keyboard enhancement flags were pushed when the terminal was taken
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+
+
+/// records that the terminal has been set up for editing, so it can be
+/// given back if the client dies without reaching its normal cleanup
+pub fn terminal_taken(keyboard_enhanced: bool){
+    KEYBOARD_ENHANCED.store(keyboard_enhanced, Ordering::SeqCst);
+    TERMINAL_TAKEN.store(true, Ordering::SeqCst);
+}
+
+/// records that the terminal has been restored through the normal path
+pub fn terminal_released(){
+    TERMINAL_TAKEN.store(false, Ordering::SeqCst);
+}
+
+/// puts the terminal back the way the shell expects it, if it is still taken.
+/// every step is attempted even if an earlier one fails, since there is no
+/// one left to report failures to
+pub fn restore_terminal(){
+    if !TERMINAL_TAKEN.swap(false, Ordering::SeqCst){
+        return;
+    }
+    let mut stdout = std::io::stdout();
+    if KEYBOARD_ENHANCED.load(Ordering::SeqCst){
+        let _ = stdout.execute(event::PopKeyboardEnhancementFlags);
+    }
+    let _ = terminal::disable_raw_mode();
+    let _ = stdout.execute(event::DisableMouseCapture);
+    let _ = stdout.execute(terminal::LeaveAlternateScreen);
+    let _ = stdout.execute(cursor::SetCursorStyle::DefaultUserShape);
+    let _ = stdout.execute(cursor::Show);
+}
+
+/// restores the terminal and writes a crash report before the default panic
+/// message is printed
+pub fn install_panic_hook(){
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        let report = format!(
+            "nlo {} crashed\n\n{}\n\nbacktrace:\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            info,
+            Backtrace::force_capture()
+        );
+        match write_crash_report(&report){
+            Some(path) => eprintln!("nlo crashed. a crash report was written to {}", path.display()),
+            None => eprintln!("nlo crashed, and the crash report could not be written:\n{report}")
+        }
+        default_hook(info);
+    }));
+}
+
+/// restores the terminal and exits when the client is asked to terminate, or
+/// its terminal goes away. documents live in the server, so nothing is lost
+pub fn handle_termination_signals() -> std::io::Result<()>{
+    let mut signals = Signals::new([SIGTERM, SIGHUP])?;
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next(){
+            restore_terminal();
+            // the shell convention for a process ended by a signal
+            std::process::exit(128 + signal);
+        }
+    });
+
+    Ok(())
+}
+
+/// writes a report to `$XDG_DATA_HOME/nlo/crash-<unix time>.txt`, or the temp
+/// directory when there is no data directory. returns the path written
+fn write_crash_report(report: &str) -> Option<PathBuf>{
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    let file_name = format!("crash-{time}.txt");
+    let directory = config::data_dir().unwrap_or_else(std::env::temp_dir);
+    let path = directory.join(file_name);
+    std::fs::create_dir_all(&directory).ok()?;
+    let mut file = std::fs::File::create(&path).ok()?;
+    file.write_all(report.as_bytes()).ok()?;
+
+    Some(path)
+}
@@ -0,0 +1,228 @@
+/// a run of changed lines between two versions of a text
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Hunk{
+    /// 0 based line in the old text where the hunk starts
+    pub old_start: usize,
+    /// the old text's lines the hunk replaces
+    pub old_lines: Vec<String>,
+    /// 0 based line in the new text where the hunk starts
+    pub new_start: usize,
+    /// how many lines of the new text the hunk covers
+    pub new_count: usize,
+}
+impl Hunk{
+    /// the new lines the hunk covers. empty for a pure deletion
+    pub fn new_lines(&self) -> std::ops::Range<usize>{
+        self.new_start..self.new_start + self.new_count
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edit{
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// the hunks turning `old` into `new`, found with Myers' algorithm on whole lines
+pub fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Hunk>{
+    // lines shared at both ends are never part of a hunk, and trimming them keeps the search small
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut hunks = Vec::new();
+    let (mut old_index, mut new_index) = (prefix, prefix);
+    let mut current: Option<Hunk> = None;
+    for edit in shortest_edit(old_middle, new_middle){
+        match edit{
+            Edit::Equal => {
+                hunks.extend(current.take());
+                old_index += 1;
+                new_index += 1;
+            }
+            Edit::Delete => {
+                current.get_or_insert_with(|| Hunk{old_start: old_index, old_lines: Vec::new(), new_start: new_index, new_count: 0})
+                    .old_lines.push(old[old_index].to_string());
+                old_index += 1;
+            }
+            Edit::Insert => {
+                current.get_or_insert_with(|| Hunk{old_start: old_index, old_lines: Vec::new(), new_start: new_index, new_count: 0})
+                    .new_count += 1;
+                new_index += 1;
+            }
+        }
+    }
+    hunks.extend(current);
+
+    hunks
+}
+
+/// the edits of a shortest edit script from `old` to `new`, in order
+fn shortest_edit(old: &[&str], new: &[&str]) -> Vec<Edit>{
+    let mut edits = Vec::with_capacity(old.len() + new.len());
+    push_edits(old, new, &mut edits);
+
+    edits
+}
+
+/// appends the edits from `old` to `new`. each step splits the texts at the
+/// middle of a shortest path and recurses, so memory stays linear in their
+/// length instead of growing with the square of the number of edits
+fn push_edits(old: &[&str], new: &[&str], edits: &mut Vec<Edit>){
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    edits.extend(std::iter::repeat_n(Edit::Equal, prefix));
+    if old.is_empty(){
+        edits.extend(std::iter::repeat_n(Edit::Insert, new.len()));
+    }else if new.is_empty(){
+        edits.extend(std::iter::repeat_n(Edit::Delete, old.len()));
+    }else{
+        // both ends differ, so there are at least two edits and each half has fewer
+        let (x, y) = middle_point(old, new);
+        push_edits(&old[..x], &new[..y], edits);
+        push_edits(&old[x..], &new[y..], edits);
+    }
+    edits.extend(std::iter::repeat_n(Edit::Equal, suffix));
+}
+
+/// a point (x, y) on a shortest path from `old` to `new` with half of its
+/// edits before it, found by searching from both ends until the paths overlap
+fn middle_point(old: &[&str], new: &[&str]) -> (usize, usize){
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let max = (n + m + 1) / 2;
+    // furthest x reached on each diagonal k = x - y, indexed by k + max + 1.
+    // backward counts x and y from the ends, so its diagonal k is forward's delta - k
+    let mut forward = vec![0isize; 2 * max as usize + 3];
+    let mut backward = vec![0isize; 2 * max as usize + 3];
+    let index = |k: isize| (k + max + 1) as usize;
+    let furthest = |v: &[isize], k: isize, d: isize| {
+        if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]){
+            v[index(k + 1)]
+        }else{
+            v[index(k - 1)] + 1
+        }
+    };
+    for d in 0..=max{
+        for k in (-d..=d).step_by(2){
+            let mut x = furthest(&forward, k, d);
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize]{
+                x += 1;
+                y += 1;
+            }
+            forward[index(k)] = x;
+            let opposite = delta - k;
+            if delta % 2 != 0 && opposite.abs() < d && x + backward[index(opposite)] >= n{
+                return (x as usize, y as usize);
+            }
+        }
+        for k in (-d..=d).step_by(2){
+            let mut x = furthest(&backward, k, d);
+            let mut y = x - k;
+            while x < n && y < m && old[(n - 1 - x) as usize] == new[(m - 1 - y) as usize]{
+                x += 1;
+                y += 1;
+            }
+            backward[index(k)] = x;
+            let opposite = delta - k;
+            if delta % 2 == 0 && opposite.abs() <= d && x + forward[index(opposite)] >= n{
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+
+    // the paths always meet before here, but deleting everything then inserting is still a valid script
+    (old.len(), 0)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    /// the length of the longest common subsequence, which a shortest script keeps
+    fn common_length(old: &[&str], new: &[&str]) -> usize{
+        let mut row = vec![0; new.len() + 1];
+        for a in old{
+            let mut diagonal = 0;
+            for (j, b) in new.iter().enumerate(){
+                let above = row[j + 1];
+                row[j + 1] = if a == b{diagonal + 1}else{above.max(row[j])};
+                diagonal = above;
+            }
+        }
+
+        row[new.len()]
+    }
+
+    fn apply(old: &[&str], new: &[&str], hunks: &[Hunk]) -> Vec<String>{
+        let mut lines: Vec<String> = old.iter().map(|line| line.to_string()).collect();
+        for hunk in hunks.iter().rev(){
+            let replaced = hunk.old_start..hunk.old_start + hunk.old_lines.len();
+            assert_eq!(lines[replaced.clone()], hunk.old_lines[..]);
+            lines.splice(replaced, new[hunk.new_lines()].iter().map(|line| line.to_string()));
+        }
+
+        lines
+    }
+
+    fn check(old: &[&str], new: &[&str]){
+        let hunks = diff_lines(old, new);
+        assert_eq!(apply(old, new, &hunks), new, "old {old:?} new {new:?}");
+        let changed: usize = hunks.iter().map(|hunk| hunk.old_lines.len() + hunk.new_count).sum();
+        let common = common_length(old, new);
+        assert_eq!(changed, old.len() + new.len() - 2 * common, "old {old:?} new {new:?}");
+    }
+
+    #[test]
+    fn empty_inputs(){
+        assert!(diff_lines(&[], &[]).is_empty());
+        assert_eq!(diff_lines(&[], &["a", "b"]), vec![Hunk{old_start: 0, old_lines: Vec::new(), new_start: 0, new_count: 2}]);
+        assert_eq!(diff_lines(&["a"], &[]), vec![Hunk{old_start: 0, old_lines: vec!["a".to_string()], new_start: 0, new_count: 0}]);
+    }
+
+    #[test]
+    fn changes_only_at_one_end(){
+        assert_eq!(diff_lines(&["a", "b", "c"], &["x", "b", "c"]), vec![Hunk{old_start: 0, old_lines: vec!["a".to_string()], new_start: 0, new_count: 1}]);
+        assert_eq!(diff_lines(&["a", "b"], &["a", "b", "c"]), vec![Hunk{old_start: 2, old_lines: Vec::new(), new_start: 2, new_count: 1}]);
+        assert_eq!(diff_lines(&["a", "b", "c"], &["a", "b"]), vec![Hunk{old_start: 2, old_lines: vec!["c".to_string()], new_start: 2, new_count: 0}]);
+        assert!(diff_lines(&["a", "b"], &["a", "b"]).is_empty());
+    }
+
+    #[test]
+    fn everything_changed(){
+        let hunks = diff_lines(&["a", "b"], &["x", "y", "z"]);
+        assert_eq!(hunks, vec![Hunk{old_start: 0, old_lines: vec!["a".to_string(), "b".to_string()], new_start: 0, new_count: 3}]);
+    }
+
+    #[test]
+    fn scripts_are_shortest(){
+        // every pair of short texts over a small alphabet, so lines repeat
+        let texts: Vec<Vec<&str>> = (0..3usize.pow(5))
+            .flat_map(|seed| (0..=5).map(move |length| (0..length).map(|i| ["a", "b", "c"][seed / 3usize.pow(i as u32) % 3]).collect()))
+            .collect();
+        for old in texts.iter().step_by(7){
+            for new in texts.iter().step_by(5){
+                check(old, new);
+            }
+        }
+    }
+
+    #[test]
+    fn large_inputs_with_many_edits(){
+        let old: Vec<String> = (0..3_000).map(|i| (i * 7919 % 1_009).to_string()).collect();
+        let new: Vec<String> = (0..3_000).map(|i| (i * 104_729 % 1_009).to_string()).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        let hunks = diff_lines(&old, &new);
+        assert_eq!(apply(&old, &new, &hunks), new);
+    }
+}
@@ -0,0 +1,208 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+
+
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GlyphKind{
+    Text,
+    /// a tab, drawn as spaces up to the next tab stop
+    Tab,
+    /// a control character, drawn as a visible escape like ^M or <0x1b>
+    Control,
+}
+
+/// how one grapheme of a line is drawn on screen
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Glyph{
+    pub text: String,
+    /// cells taken on screen
+    pub width: usize,
+    pub kind: GlyphKind,
+}
+
+/// the glyph for a grapheme drawn starting at cell `column`. tab stops are
+/// every `tab_width` cells from column 0
+pub fn glyph(grapheme: &str, column: usize, tab_width: usize) -> Glyph{
+    if grapheme == "\t"{
+        let tab_width = tab_width.max(1);
+        let width = tab_width - column % tab_width;
+        return Glyph{text: " ".repeat(width), width, kind: GlyphKind::Tab};
+    }
+    match control_text(grapheme){
+        Some(text) => Glyph{width: text.len(), text, kind: GlyphKind::Control},
+        None => Glyph{text: grapheme.to_string(), width: grapheme.width(), kind: GlyphKind::Text}
+    }
+}
+
+/// the visible form of a control character. C0 controls use caret notation,
+/// except escape, whose ^[ is easily misread as a bracket
+fn control_text(grapheme: &str) -> Option<String>{
+    let c = grapheme.chars().next()?;
+    if !c.is_control(){
+        return None;
+    }
+    Some(
+        match c{
+            '\u{1b}' => format!("<{:#04x}>", c as u32),
+            '\0'..='\u{1f}' => format!("^{}", (c as u8 + 0x40) as char),
+            '\u{7f}' => "^?".to_string(),
+            _ => format!("<{:#04x}>", c as u32)
+        }
+    )
+}
+
+/// the glyphs of a line whose first grapheme is drawn at cell `start_column`
+pub fn glyphs(line: &str, start_column: usize, tab_width: usize) -> Vec<Glyph>{
+    let mut column = start_column;
+    line.graphemes(true)
+        .map(|grapheme| {
+            let glyph = glyph(grapheme, column, tab_width);
+            column += glyph.width;
+            glyph
+        })
+        .collect()
+}
+
+/// how many cells into the line grapheme `x` is drawn
+pub fn cell_of(line: &str, x: usize, start_column: usize, tab_width: usize) -> usize{
+    let line_cells: usize = glyphs(line, start_column, tab_width).iter()
+        .take(x)
+        .map(|glyph| glyph.width)
+        .sum();
+    // positions past the line's end, like the cursor after the last grapheme, are one cell each
+    let past_end = x.saturating_sub(line.graphemes(true).count());
+
+    line_cells + past_end
+}
+
+/// the grapheme index drawn at `cell` cells into the line. cells past the
+/// line's end count as one grapheme each, mirroring `cell_of`
+pub fn column_at(line: &str, cell: usize, start_column: usize, tab_width: usize) -> usize{
+    let mut used = 0;
+    let glyphs = glyphs(line, start_column, tab_width);
+    for (x, glyph) in glyphs.iter().enumerate(){
+        used += glyph.width;
+        if cell < used{
+            return x;
+        }
+    }
+
+    glyphs.len() + (cell - used)
+}
+
+/// the cells of indentation before a line's first non whitespace grapheme.
+/// None for a line that is empty or all whitespace
+pub fn indent_cells(line: &str, tab_width: usize) -> Option<usize>{
+    let mut column = 0;
+    for grapheme in line.graphemes(true){
+        if !grapheme.chars().all(char::is_whitespace){
+            return Some(column);
+        }
+        column += glyph(grapheme, column, tab_width).width;
+    }
+
+    None
+}
+
+/// the grapheme index where a line's trailing whitespace starts, if it has any
+pub fn trailing_whitespace_start(line: &str) -> Option<usize>{
+    let graphemes: Vec<&str> = line.graphemes(true).collect();
+    let content_end = graphemes.iter()
+        .rposition(|grapheme| !grapheme.chars().all(char::is_whitespace))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+
+    (content_end < graphemes.len()).then_some(content_end)
+}
+
+/// guesses the indent width lines were written with. tab indented text uses
+/// the tab width, and space indented text the largest step all indents share
+pub fn detect_indent_width(lines: &[&str], tab_width: usize) -> usize{
+    if lines.iter().any(|line| line.starts_with('\t')){
+        return tab_width;
+    }
+
+    fn gcd(a: usize, b: usize) -> usize{
+        if b == 0{a}else{gcd(b, a % b)}
+    }
+    let step = lines.iter()
+        .filter_map(|line| indent_cells(line, tab_width))
+        .fold(0, gcd);
+    match step{
+        // unindented text, or indents too ragged to share a step
+        0 | 1 => tab_width,
+        step => step.min(8)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn control_characters_are_drawn_visibly(){
+        assert_eq!(glyph("\r", 0, 4), Glyph{text: "^M".to_string(), width: 2, kind: GlyphKind::Control});
+        assert_eq!(glyph("\u{1b}", 0, 4), Glyph{text: "<0x1b>".to_string(), width: 6, kind: GlyphKind::Control});
+        assert_eq!(glyph("\u{7f}", 0, 4).text, "^?");
+        assert_eq!(glyph("\u{85}", 0, 4).text, "<0x85>");
+        assert_eq!(glyph("a", 0, 4).kind, GlyphKind::Text);
+    }
+
+    #[test]
+    fn tabs_reach_the_next_stop(){
+        assert_eq!(glyph("\t", 0, 4).width, 4);
+        assert_eq!(glyph("\t", 5, 4).width, 3);
+        assert_eq!(glyph("\t", 8, 4).width, 4);
+        // a zero tab width still moves the cursor a cell
+        assert_eq!(glyph("\t", 3, 0).width, 1);
+    }
+
+    #[test]
+    fn tab_stops_count_from_the_start_column(){
+        let widths = |line, start| glyphs(line, start, 4).iter().map(|glyph| glyph.width).collect::<Vec<_>>();
+        assert_eq!(widths("a\tb", 0), vec![1, 3, 1]);
+        assert_eq!(widths("a\tb", 1), vec![1, 2, 1]);
+        assert_eq!(widths("a\tb", 3), vec![1, 4, 1]);
+        assert_eq!(cell_of("a\tb", 2, 1, 4), 3);
+        assert_eq!(column_at("a\tb", 2, 1, 4), 1);
+        assert_eq!(column_at("a\tb", 3, 1, 4), 2);
+    }
+
+    #[test]
+    fn wide_and_control_glyphs_take_their_width(){
+        assert_eq!(glyph("中", 0, 4).width, 2);
+        assert_eq!(cell_of("中a", 1, 0, 4), 2);
+        // both cells of a wide grapheme belong to it
+        assert_eq!(column_at("中a", 0, 0, 4), 0);
+        assert_eq!(column_at("中a", 1, 0, 4), 0);
+        assert_eq!(column_at("中a", 2, 0, 4), 1);
+        assert_eq!(cell_of("\u{1b}x", 1, 0, 4), 6);
+        assert_eq!(column_at("\u{1b}x", 5, 0, 4), 0);
+        assert_eq!(column_at("\u{1b}x", 6, 0, 4), 1);
+    }
+
+    #[test]
+    fn positions_past_the_end_are_a_cell_each(){
+        assert_eq!(cell_of("ab", 4, 0, 4), 4);
+        assert_eq!(column_at("ab", 4, 0, 4), 4);
+        assert_eq!(cell_of("中", 2, 0, 4), 3);
+    }
+
+    #[test]
+    fn cells_and_columns_round_trip(){
+        for line in ["", "plain", "a\tb\t\tc", "中a👍\u{1b}\r", "e\u{301}\tx"]{
+            for start in 0..5{
+                for x in 0..line.graphemes(true).count() + 3{
+                    assert_eq!(column_at(line, cell_of(line, x, start, 4), start, 4), x, "{line:?} from {start} at {x}");
+                }
+            }
+        }
+    }
+}
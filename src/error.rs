@@ -0,0 +1,114 @@
+use crate::config::ConfigError;
+use crate::keymap::KeymapError;
+use crate::theme::ThemeError;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+
+
+/// everything that can go wrong in the client. most errors end the session,
+/// but some only fail the action that caused them, see `is_fatal`
+#[derive(Debug)]
+pub enum ClientError{
+    /// bad command line arguments
+    Usage(String),
+    /// the file to edit could not be found
+    FilePath{path: PathBuf, error: std::io::Error},
+    /// no server to connect to
+    Connect(std::io::Error),
+    /// the connection to the server failed or was closed
+    Transport(std::io::Error),
+    /// a request could not be encoded for the server
+    ProtocolEncode(ron::Error),
+    /// a server response could not be decoded
+    ProtocolDecode(ron::error::SpannedError),
+    /// the server refused a request the client can't continue without
+    Server(String),
+    /// drawing to or reading from the terminal failed
+    Terminal(std::io::Error),
+    Config(ConfigError),
+    Keymap(KeymapError),
+    Theme(ThemeError),
+}
+impl ClientError{
+    /// false for errors that only spoil one exchange with the server, which are
+    /// reported in the util bar while editing goes on
+    pub fn is_fatal(&self) -> bool{
+        !matches!(self, ClientError::ProtocolEncode(_) | ClientError::ProtocolDecode(_))
+    }
+}
+impl fmt::Display for ClientError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            ClientError::Usage(message) => {
+                write!(f, "{}", message)
+            }
+            ClientError::FilePath{path, error} => {
+                write!(f, "could not open {}: {}", path.display(), error)
+            }
+            ClientError::Connect(error) => {
+                write!(f, "could not connect to the server. make sure you have started an nlo_server process: {}", error)
+            }
+            ClientError::Transport(error) => {
+                write!(f, "lost the connection to the server: {}", error)
+            }
+            ClientError::ProtocolEncode(error) => {
+                write!(f, "could not encode a request for the server: {}", error)
+            }
+            ClientError::ProtocolDecode(error) => {
+                write!(f, "could not decode a response from the server: {}", error)
+            }
+            ClientError::Server(message) => {
+                write!(f, "the server failed: {}", message)
+            }
+            ClientError::Terminal(error) => {
+                write!(f, "terminal error: {}", error)
+            }
+            ClientError::Config(error) => write!(f, "{}", error),
+            ClientError::Keymap(error) => write!(f, "{}", error),
+            ClientError::Theme(error) => write!(f, "{}", error),
+        }
+    }
+}
+impl Error for ClientError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self{
+            ClientError::FilePath{error, ..}
+            | ClientError::Connect(error)
+            | ClientError::Transport(error)
+            | ClientError::Terminal(error) => Some(error),
+            ClientError::ProtocolEncode(error) => Some(error),
+            ClientError::ProtocolDecode(error) => Some(error),
+            ClientError::Config(error) => Some(error),
+            ClientError::Keymap(error) => Some(error),
+            ClientError::Theme(error) => Some(error),
+            ClientError::Usage(_) | ClientError::Server(_) => None,
+        }
+    }
+}
+impl From<ron::Error> for ClientError{
+    fn from(error: ron::Error) -> Self{
+        ClientError::ProtocolEncode(error)
+    }
+}
+impl From<ron::error::SpannedError> for ClientError{
+    fn from(error: ron::error::SpannedError) -> Self{
+        ClientError::ProtocolDecode(error)
+    }
+}
+impl From<ConfigError> for ClientError{
+    fn from(error: ConfigError) -> Self{
+        ClientError::Config(error)
+    }
+}
+impl From<KeymapError> for ClientError{
+    fn from(error: KeymapError) -> Self{
+        ClientError::Keymap(error)
+    }
+}
+impl From<ThemeError> for ClientError{
+    fn from(error: ThemeError) -> Self{
+        ClientError::Theme(error)
+    }
+}
@@ -0,0 +1,144 @@
+use crate::diff::Hunk;
+use unicode_segmentation::UnicodeSegmentation;
+
+
+
+/// a replacement of the text between two (x, y) positions, x counting graphemes
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TextEdit{
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub text: String,
+}
+
+/// the edits turning `old` lines into `new` lines, one per hunk, with lines
+/// separated by `line_ending`. they are ordered last first, so applying each
+/// leaves the positions of the rest valid
+pub fn minimal_edits(old: &[&str], new: &[&str], hunks: &[Hunk], line_ending: &str) -> Vec<TextEdit>{
+    let line_end = |line: usize| (old[line].graphemes(true).count(), line);
+    hunks.iter().rev()
+        .map(|hunk| {
+            let start = hunk.old_start;
+            let end = hunk.old_start + hunk.old_lines.len();
+            let lines = &new[hunk.new_lines()];
+            if end < old.len(){
+                // whole lines up to the start of the next unchanged one
+                TextEdit{start: (0, start), end: (0, end), text: lines.iter().map(|line| format!("{line}{line_ending}")).collect()}
+            }else if lines.is_empty(){
+                // removing the last lines takes the line break before them too
+                let from = if start == 0{(0, 0)}else{line_end(start - 1)};
+                TextEdit{start: from, end: line_end(old.len() - 1), text: String::new()}
+            }else if start < end{
+                TextEdit{start: (0, start), end: line_end(old.len() - 1), text: lines.join(line_ending)}
+            }else if old.is_empty(){
+                TextEdit{start: (0, 0), end: (0, 0), text: lines.join(line_ending)}
+            }else{
+                // appending after the last line
+                let last = line_end(old.len() - 1);
+                TextEdit{start: last, end: last, text: format!("{line_ending}{}", lines.join(line_ending))}
+            }
+        })
+        .collect()
+}
+
+/// where an old line ends up once the hunks are applied. a line inside a
+/// changed hunk moves to the hunk's first new line
+pub fn line_after(hunks: &[Hunk], line: usize) -> usize{
+    let mut shift: isize = 0;
+    for hunk in hunks{
+        let end = hunk.old_start + hunk.old_lines.len();
+        if line < hunk.old_start{
+            break;
+        }
+        if line < end{
+            return hunk.new_start;
+        }
+        shift += hunk.new_count as isize - hunk.old_lines.len() as isize;
+    }
+
+    line.saturating_add_signed(shift)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::diff::diff_lines;
+
+    /// the byte offset of an (x, y) position in `text`
+    fn offset(text: &str, (x, y): (usize, usize)) -> usize{
+        let before: usize = text.split('\n').take(y).map(|line| line.len() + 1).sum();
+        let line = text.split('\n').nth(y).unwrap();
+
+        before + line.graphemes(true).take(x).map(str::len).sum::<usize>()
+    }
+
+    /// applies the edits for `old` to `new` to the old text, as the server would
+    fn formatted(old: &str, new: &str, line_ending: &str) -> String{
+        let old_lines: Vec<&str> = old.lines().collect();
+        let new_lines: Vec<&str> = new.lines().collect();
+        let hunks = diff_lines(&old_lines, &new_lines);
+        let mut text = old.to_string();
+        for edit in minimal_edits(&old_lines, &new_lines, &hunks, line_ending){
+            let range = offset(&text, edit.start)..offset(&text, edit.end);
+            text.replace_range(range, &edit.text);
+        }
+
+        text
+    }
+
+    #[test]
+    fn edits_reproduce_the_new_text(){
+        let texts = ["", "a", "a\nb", "a\nb\nc\n", "x\na\nb\nc", "a\nb\nc\nd", "b\nc", "a\nc", "é\nb", "a\nb\nx\ny", "z", "a\nz\nc\nq"];
+        for line_ending in ["\n", "\r\n"]{
+            for old in texts{
+                let old = old.replace('\n', line_ending);
+                for new in texts{
+                    // a final line break belongs to the document, not to any line, so it stays
+                    let text = formatted(&old, new, line_ending);
+                    let text = if old.ends_with(line_ending){text.strip_suffix(line_ending).unwrap_or(&text)}else{&text};
+                    // split on the document's line ending only, so a stray \n would show up in a line
+                    let lines: Vec<&str> = if text.is_empty(){Vec::new()}else{text.split(line_ending).collect()};
+                    assert_eq!(lines, new.lines().collect::<Vec<_>>(), "old {old:?} new {new:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unchanged_text_needs_no_edits(){
+        let lines = ["a", "b"];
+        assert!(minimal_edits(&lines, &lines, &diff_lines(&lines, &lines), "\n").is_empty());
+    }
+
+    #[test]
+    fn edits_are_ordered_last_first(){
+        let old = ["a", "b", "c", "d"];
+        let new = ["x", "b", "c", "y"];
+        let edits = minimal_edits(&old, &new, &diff_lines(&old, &new), "\n");
+        assert_eq!(edits, vec![
+            TextEdit{start: (0, 3), end: (1, 3), text: "y".to_string()},
+            TextEdit{start: (0, 0), end: (0, 1), text: "x\n".to_string()},
+        ]);
+    }
+
+    #[test]
+    fn trailing_lines(){
+        let old = ["a", "b", "c"];
+        let removed = ["a"];
+        assert_eq!(minimal_edits(&old, &removed, &diff_lines(&old, &removed), "\n"), vec![TextEdit{start: (1, 0), end: (1, 2), text: String::new()}]);
+        let added = ["a", "b", "c", "d"];
+        assert_eq!(minimal_edits(&old, &added, &diff_lines(&old, &added), "\n"), vec![TextEdit{start: (1, 2), end: (1, 2), text: "\nd".to_string()}]);
+    }
+
+    #[test]
+    fn lines_follow_the_hunks(){
+        let old = ["a", "b", "c", "d"];
+        let new = ["x", "y", "a", "c", "d"];
+        let hunks = diff_lines(&old, &new);
+        // b was deleted, so it lands where its hunk starts
+        assert_eq!((0..old.len()).map(|line| line_after(&hunks, line)).collect::<Vec<_>>(), vec![2, 3, 3, 4]);
+        assert_eq!(line_after(&[], 5), 5);
+        let all = diff_lines(&old, &["z"]);
+        assert_eq!((0..old.len()).map(|line| line_after(&all, line)).collect::<Vec<_>>(), vec![0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::annotations::{Annotation, AnnotationKind};
+use crate::diff::{self, Hunk};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+
+
+/// compares the document against its last committed version, for the
+/// version control markers in the sign column
+pub struct GitGutter{
+    path: PathBuf,
+    /// the file's text at HEAD
+    head_text: String,
+    /// the document text the hunks were computed from, None until the first diff
+    diffed_text: Option<String>,
+    hunks: Vec<Hunk>,
+}
+impl GitGutter{
+    /// reads the file's HEAD version. None if the file is not in a git
+    /// repository, not committed yet, or git is not installed
+    pub fn load(path: &Path) -> Option<Self>{
+        let head_text = head_blob(path)?;
+        Some(Self{
+            path: path.to_path_buf(),
+            head_text,
+            diffed_text: None,
+            hunks: Vec::new(),
+        })
+    }
+
+    /// rereads the HEAD version, in case a commit was made since loading
+    pub fn reload_head(&mut self){
+        if let Some(head_text) = head_blob(&self.path){
+            self.head_text = head_text;
+            self.diffed_text = None;
+        }
+    }
+
+    /// diffs the document against HEAD. returns false if the text is unchanged
+    /// since the last update, so the hunks are too
+    pub fn update(&mut self, text: &str) -> bool{
+        if self.diffed_text.as_deref() == Some(text){
+            return false;
+        }
+        let old: Vec<&str> = self.head_text.lines().collect();
+        let new: Vec<&str> = text.lines().collect();
+        self.hunks = diff::diff_lines(&old, &new);
+        self.diffed_text = Some(text.to_string());
+
+        true
+    }
+
+    /// sign column markers for every changed line
+    pub fn annotations(&self) -> Vec<(usize, Annotation)>{
+        let mut annotations = Vec::new();
+        for hunk in &self.hunks{
+            let kind = if hunk.old_lines.is_empty(){AnnotationKind::VcsAdded}else{AnnotationKind::VcsModified};
+            if hunk.new_count == 0{
+                // removed lines leave nothing to mark, so the line above them is marked instead
+                annotations.push((hunk.new_start.saturating_sub(1), Annotation::new(AnnotationKind::VcsDeleted, None)));
+            }
+            for line in hunk.new_lines(){
+                annotations.push((line, Annotation::new(kind, None)));
+            }
+        }
+
+        annotations
+    }
+
+    /// the first line of the next hunk below `line`, wrapping around to the first hunk
+    pub fn next_hunk(&self, line: usize) -> Option<usize>{
+        let starts = self.hunk_starts();
+        starts.iter().copied().find(|start| *start > line).or(starts.first().copied())
+    }
+
+    /// the first line of the previous hunk above `line`, wrapping around to the last hunk
+    pub fn previous_hunk(&self, line: usize) -> Option<usize>{
+        let starts = self.hunk_starts();
+        starts.iter().copied().rfind(|start| *start < line).or(starts.last().copied())
+    }
+
+    fn hunk_starts(&self) -> Vec<usize>{
+        self.hunks.iter()
+            .map(|hunk| if hunk.new_count == 0{hunk.new_start.saturating_sub(1)}else{hunk.new_start})
+            .collect()
+    }
+
+    /// the hunk covering a line, including a deletion marked on it
+    pub fn hunk_at(&self, line: usize) -> Option<&Hunk>{
+        self.hunks.iter().find(|hunk| {
+            hunk.new_lines().contains(&line) || (hunk.new_count == 0 && hunk.new_start.saturating_sub(1) == line)
+        })
+    }
+}
+
+/// the checked out branch of the repository holding a file, or the short
+/// commit hash when HEAD is detached
+pub fn branch(path: &Path) -> Option<String>{
+    let directory = path.parent()?;
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C").arg(directory)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !text.is_empty()).then_some(text)
+    };
+
+    git(&["symbolic-ref", "--short", "-q", "HEAD"]).or_else(|| git(&["rev-parse", "--short", "HEAD"]))
+}
+
+/// the committed text of a file, read with git plumbing from the local repository
+fn head_blob(path: &Path) -> Option<String>{
+    let directory = path.parent()?;
+    let file_name = path.file_name()?.to_str()?;
+    let output = Command::new("git")
+        .arg("-C").arg(directory)
+        .arg("cat-file").arg("blob")
+        // a ./ path is resolved from the -C directory rather than the repository root
+        .arg(format!("HEAD:./{file_name}"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success(){
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// the top directory of the repository holding a directory
+pub fn toplevel(directory: &Path) -> Option<PathBuf>{
+    let output = Command::new("git")
+        .arg("-C").arg(directory)
+        .arg("rev-parse").arg("--show-toplevel")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    (output.status.success() && !text.is_empty()).then(|| PathBuf::from(text))
+}
+
+/// the files of a repository, relative to its top directory: tracked files
+/// plus untracked ones that .gitignore doesn't exclude
+pub fn project_files(root: &Path) -> Option<Vec<String>>{
+    let output = Command::new("git")
+        .arg("-C").arg(root)
+        .arg("ls-files").arg("-z").arg("--cached").arg("--others").arg("--exclude-standard")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success(){
+        return None;
+    }
+    let mut files: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|file| !file.is_empty())
+        // tracked files deleted from the working tree are still listed
+        .filter(|file| root.join(file).is_file())
+        .map(str::to_string)
+        .collect();
+    // files with unmerged changes are listed once per conflict stage
+    files.dedup();
+
+    Some(files)
+}
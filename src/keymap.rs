@@ -0,0 +1,596 @@
+use crate::application::{ClientAction, Mode, VIEW_SCROLL_AMOUNT};
+use crate::modal::{Motion, Operator, Profile};
+use crate::config;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+
+
+const KEYMAP_FILE_NAME: &str = "keymap.ron";
+/// how long to wait for the next key of a partially typed sequence
+const DEFAULT_SEQUENCE_TIMEOUT_MS: u64 = 1000;
+
+
+
+/// a single key press, including any modifiers held while it was pressed
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord{
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+impl KeyChord{
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self{
+        // the case of a character already encodes shift, so drop it to let
+        // "G" match regardless of whether the terminal reports SHIFT
+        match code{
+            KeyCode::Char(_) | KeyCode::BackTab => Self{code, modifiers: modifiers.difference(KeyModifiers::SHIFT)},
+            _ => Self{code, modifiers}
+        }
+    }
+
+    pub fn from_key_event(key_event: KeyEvent) -> Self{
+        Self::new(key_event.code, key_event.modifiers)
+    }
+
+    pub fn code(&self) -> KeyCode{
+        self.code
+    }
+}
+impl FromStr for KeyChord{
+    type Err = String;
+
+    /// parses chords like "Ctrl-s", "Alt-Down", "Shift-Tab" or "F1"
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = s.split('-').collect();
+        // a trailing empty part means the key itself was '-', as in "Ctrl--"
+        let key = if s.ends_with('-') && parts.len() > 1{
+            parts.truncate(parts.len() - 2);
+            "-"
+        }else{
+            parts.pop().unwrap_or("")
+        };
+        for modifier in parts{
+            modifiers |= match modifier.to_lowercase().as_str(){
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "a" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{s}'"))
+            };
+        }
+
+        let code = match key.to_lowercase().as_str(){
+            "backspace" => KeyCode::Backspace,
+            "enter" | "return" => KeyCode::Enter,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "tab" => if modifiers.contains(KeyModifiers::SHIFT){KeyCode::BackTab}else{KeyCode::Tab},
+            "backtab" => KeyCode::BackTab,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "minus" => KeyCode::Char('-'),
+            lower => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()){
+                    (Some(c), None) => {
+                        if modifiers.contains(KeyModifiers::SHIFT){
+                            KeyCode::Char(c.to_ascii_uppercase())
+                        }else{
+                            KeyCode::Char(c)
+                        }
+                    }
+                    _ => {
+                        match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()){
+                            Some(n) if (1..=24).contains(&n) => KeyCode::F(n),
+                            _ => return Err(format!("unknown key '{key}' in '{s}'"))
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+impl fmt::Display for KeyChord{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        if self.modifiers.contains(KeyModifiers::CONTROL){write!(f, "Ctrl-")?;}
+        if self.modifiers.contains(KeyModifiers::ALT){write!(f, "Alt-")?;}
+        if self.modifiers.contains(KeyModifiers::SHIFT){write!(f, "Shift-")?;}
+        match self.code{
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::BackTab => write!(f, "Shift-Tab"),
+            code => write!(f, "{code:?}")
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KeymapError{
+    Io{path: PathBuf, error: std::io::Error},
+    Parse{path: PathBuf, error: ron::error::SpannedError},
+    InvalidKey{path: PathBuf, mode: &'static str, error: String},
+}
+impl fmt::Display for KeymapError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            KeymapError::Io{path, error} => {
+                write!(f, "could not read keymap {}: {}", path.display(), error)
+            }
+            KeymapError::Parse{path, error} => {
+                write!(f, "could not parse keymap {}: {}", path.display(), error)
+            }
+            KeymapError::InvalidKey{path, mode, error} => {
+                write!(f, "invalid key in {} section of keymap {}: {}", mode, path.display(), error)
+            }
+        }
+    }
+}
+impl Error for KeymapError{}
+
+/// parses a space separated key sequence, like "Ctrl-x Ctrl-s" or "Space f f"
+fn parse_key_sequence(s: &str) -> Result<Vec<KeyChord>, String>{
+    let sequence = s.split_whitespace()
+        .map(|chord| chord.parse::<KeyChord>())
+        .collect::<Result<Vec<KeyChord>, String>>()?;
+    if sequence.is_empty(){
+        return Err("empty key sequence".to_string());
+    }
+
+    Ok(sequence)
+}
+
+/// the on disk layout of a keymap file. each section maps key sequence strings
+/// to `ClientAction` names, and overrides the matching default bindings
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct KeymapFile{
+    /// the set of default bindings the file's sections are applied over
+    profile: Option<Profile>,
+    /// milliseconds to wait for the rest of a key sequence
+    timeout: Option<u64>,
+    normal: HashMap<String, ClientAction>,
+    visual: HashMap<String, ClientAction>,
+    undo_history: HashMap<String, ClientAction>,
+    popup: HashMap<String, ClientAction>,
+    file_picker: HashMap<String, ClientAction>,
+    insert: HashMap<String, ClientAction>,
+    warning: HashMap<String, ClientAction>,
+    command: HashMap<String, ClientAction>,
+    find_replace: HashMap<String, ClientAction>,
+    goto: HashMap<String, ClientAction>,
+}
+
+pub struct Keymap{
+    profile: Profile,
+    timeout: Duration,
+    normal: HashMap<Vec<KeyChord>, ClientAction>,
+    visual: HashMap<Vec<KeyChord>, ClientAction>,
+    undo_history: HashMap<Vec<KeyChord>, ClientAction>,
+    popup: HashMap<Vec<KeyChord>, ClientAction>,
+    file_picker: HashMap<Vec<KeyChord>, ClientAction>,
+    insert: HashMap<Vec<KeyChord>, ClientAction>,
+    warning: HashMap<Vec<KeyChord>, ClientAction>,
+    command: HashMap<Vec<KeyChord>, ClientAction>,
+    find_replace: HashMap<Vec<KeyChord>, ClientAction>,
+    goto: HashMap<Vec<KeyChord>, ClientAction>,
+}
+impl Default for Keymap{
+    fn default() -> Self{
+        Self::with_profile(Profile::Modeless)
+    }
+}
+impl Keymap{
+    /// the built in bindings for a profile
+    pub fn with_profile(profile: Profile) -> Self{
+        let mut keymap = Self{
+            profile,
+            timeout: Duration::from_millis(DEFAULT_SEQUENCE_TIMEOUT_MS),
+            normal: HashMap::new(),
+            visual: HashMap::new(),
+            undo_history: HashMap::new(),
+            popup: HashMap::new(),
+            file_picker: HashMap::new(),
+            insert: HashMap::new(),
+            warning: HashMap::new(),
+            command: HashMap::new(),
+            find_replace: HashMap::new(),
+            goto: HashMap::new(),
+        };
+
+        use KeyCode::*;
+        let ctrl = KeyModifiers::CONTROL;
+        let alt = KeyModifiers::ALT;
+        let none = KeyModifiers::NONE;
+
+        // every mode
+        for bindings in [&mut keymap.insert, &mut keymap.warning, &mut keymap.command, &mut keymap.find_replace, &mut keymap.goto, &mut keymap.normal, &mut keymap.visual, &mut keymap.undo_history, &mut keymap.popup, &mut keymap.file_picker]{
+            bindings.insert(vec![KeyChord::new(F(1), none)], ClientAction::ToggleKeyHelp);
+        }
+
+        // Insert Mode
+        let insert = &mut keymap.insert;
+        insert.insert(vec![KeyChord::new(Right,         ctrl)], ClientAction::MoveCursorWordEnd);
+        insert.insert(vec![KeyChord::new(Left,          ctrl)], ClientAction::MoveCursorWordStart);
+        insert.insert(vec![KeyChord::new(Backspace,     ctrl)], ClientAction::BackspaceWord);
+        insert.insert(vec![KeyChord::new(Delete,        ctrl)], ClientAction::DeleteWord);
+        insert.insert(vec![KeyChord::new(Home,          ctrl)], ClientAction::MoveCursorDocumentStart);
+        insert.insert(vec![KeyChord::new(End,           ctrl)], ClientAction::MoveCursorDocumentEnd);
+        insert.insert(vec![KeyChord::new(Char('q'),     ctrl)], ClientAction::Quit);
+        insert.insert(vec![KeyChord::new(Char('s'),     ctrl)], ClientAction::Save);
+        insert.insert(vec![KeyChord::new(Char('g'),     ctrl)], ClientAction::SetModeGoto);
+        insert.insert(vec![KeyChord::new(Char('f'),     ctrl)], ClientAction::SetModeFindReplace);
+        insert.insert(vec![KeyChord::new(Char('l'),     ctrl)], ClientAction::DisplayLineNumbers);
+        insert.insert(vec![KeyChord::new(Char('k'),     ctrl)], ClientAction::DisplayStatusBar);
+        insert.insert(vec![KeyChord::new(Char('z'),     alt)],  ClientAction::ToggleSoftWrap);
+        insert.insert(vec![KeyChord::new(Char('w'),     alt)],  ClientAction::ToggleVisibleWhitespace);
+        insert.insert(vec![KeyChord::new(Char('b'),     ctrl)], ClientAction::ToggleBookmark);
+        insert.insert(vec![KeyChord::new(F(9),          none)], ClientAction::ToggleBreakpoint);
+        insert.insert(vec![KeyChord::new(Char('n'),     alt)],  ClientAction::NextHunk);
+        insert.insert(vec![KeyChord::new(Char('p'),     alt)],  ClientAction::PreviousHunk);
+        insert.insert(vec![KeyChord::new(Char('h'),     alt)],  ClientAction::PreviewHunk);
+        insert.insert(vec![KeyChord::new(Char('o'),     ctrl)], ClientAction::SetModeCommand);
+        insert.insert(vec![KeyChord::new(Char('p'),     ctrl)], ClientAction::SetModeFilePicker);
+        insert.insert(vec![KeyChord::new(Char('z'),     ctrl)], ClientAction::Undo);
+        insert.insert(vec![KeyChord::new(Char('y'),     ctrl)], ClientAction::Redo);
+        insert.insert(vec![KeyChord::new(Char('c'),     ctrl)], ClientAction::Copy);
+        insert.insert(vec![KeyChord::new(Char('x'),     ctrl)], ClientAction::Cut);
+        insert.insert(vec![KeyChord::new(Char('v'),     ctrl)], ClientAction::Paste);
+        insert.insert(vec![KeyChord::new(Char('y'),     alt)],  ClientAction::PasteCycle);
+        insert.insert(vec![KeyChord::new(Char('r'),     ctrl)], ClientAction::SelectRegister);
+        insert.insert(vec![KeyChord::new(F(3),          none)], ClientAction::ToggleMacroRecording);
+        insert.insert(vec![KeyChord::new(F(4),          none)], ClientAction::PlayMacro);
+        insert.insert(vec![KeyChord::new(Down,          alt)],  ClientAction::ScrollViewDown(VIEW_SCROLL_AMOUNT));
+        insert.insert(vec![KeyChord::new(Left,          alt)],  ClientAction::ScrollViewLeft(VIEW_SCROLL_AMOUNT));
+        insert.insert(vec![KeyChord::new(Right,         alt)],  ClientAction::ScrollViewRight(VIEW_SCROLL_AMOUNT));
+        insert.insert(vec![KeyChord::new(Up,            alt)],  ClientAction::ScrollViewUp(VIEW_SCROLL_AMOUNT));
+        insert.insert(vec![KeyChord::new(Tab,           none)], ClientAction::InsertTab);
+        insert.insert(vec![KeyChord::new(Enter,         none)], ClientAction::InsertNewline);
+        insert.insert(vec![KeyChord::new(Delete,        none)], ClientAction::Delete);
+        insert.insert(vec![KeyChord::new(Backspace,     none)], ClientAction::Backspace);
+        insert.insert(vec![KeyChord::new(Up,            none)], ClientAction::MoveCursorUp);
+        insert.insert(vec![KeyChord::new(Down,          none)], ClientAction::MoveCursorDown);
+        insert.insert(vec![KeyChord::new(Left,          none)], ClientAction::MoveCursorLeft);
+        insert.insert(vec![KeyChord::new(Right,         none)], ClientAction::MoveCursorRight);
+        insert.insert(vec![KeyChord::new(PageUp,        none)], ClientAction::MoveCursorPageUp);
+        insert.insert(vec![KeyChord::new(PageDown,      none)], ClientAction::MoveCursorPageDown);
+        insert.insert(vec![KeyChord::new(Home,          none)], ClientAction::MoveCursorLineStart);
+        insert.insert(vec![KeyChord::new(End,           none)], ClientAction::MoveCursorLineEnd);
+
+        // Warning Mode
+        let warning = &mut keymap.warning;
+        warning.insert(vec![KeyChord::new(Char('q'),    ctrl)], ClientAction::QuitIgnoringChanges);
+        warning.insert(vec![KeyChord::new(Esc,          none)], ClientAction::WarningModeExit);
+
+        // Goto Mode
+        let goto = &mut keymap.goto;
+        goto.insert(vec![KeyChord::new(Esc,             none)], ClientAction::GotoModeExit);
+        goto.insert(vec![KeyChord::new(Enter,           none)], ClientAction::GotoModeAccept);
+        goto.insert(vec![KeyChord::new(Backspace,       none)], ClientAction::GotoModeBackspace);
+        goto.insert(vec![KeyChord::new(Delete,          none)], ClientAction::GotoModeDelete);
+        goto.insert(vec![KeyChord::new(Right,           none)], ClientAction::GotoModeMoveCursorRight);
+        goto.insert(vec![KeyChord::new(Left,            none)], ClientAction::GotoModeMoveCursorLeft);
+        goto.insert(vec![KeyChord::new(Home,            none)], ClientAction::GotoModeMoveCursorLineStart);
+        goto.insert(vec![KeyChord::new(End,             none)], ClientAction::GotoModeMoveCursorLineEnd);
+
+        // FindReplace Mode
+        let find_replace = &mut keymap.find_replace;
+        find_replace.insert(vec![KeyChord::new(Esc,       none)], ClientAction::FindReplaceModeExit);
+        find_replace.insert(vec![KeyChord::new(Tab,       none)], ClientAction::FindReplaceModeSwitchUtilBarFocus);
+        find_replace.insert(vec![KeyChord::new(Up,        none)], ClientAction::FindReplaceModePreviousInstance);
+        find_replace.insert(vec![KeyChord::new(Down,      none)], ClientAction::FindReplaceModeNextInstance);
+        find_replace.insert(vec![KeyChord::new(Backspace, none)], ClientAction::FindReplaceModeBackspace);
+        find_replace.insert(vec![KeyChord::new(Delete,    none)], ClientAction::FindReplaceModeDelete);
+        find_replace.insert(vec![KeyChord::new(Right,     none)], ClientAction::FindReplaceModeMoveCursorRight);
+        find_replace.insert(vec![KeyChord::new(Left,      none)], ClientAction::FindReplaceModeMoveCursorLeft);
+        find_replace.insert(vec![KeyChord::new(Home,      none)], ClientAction::FindReplaceModeMoveCursorLineStart);
+        find_replace.insert(vec![KeyChord::new(Right,     ctrl)], ClientAction::FindReplaceModeMoveCursorWordEnd);
+        find_replace.insert(vec![KeyChord::new(Left,      ctrl)], ClientAction::FindReplaceModeMoveCursorWordStart);
+        find_replace.insert(vec![KeyChord::new(Backspace, ctrl)], ClientAction::FindReplaceModeBackspaceWord);
+        find_replace.insert(vec![KeyChord::new(Delete,    ctrl)], ClientAction::FindReplaceModeDeleteWord);
+        find_replace.insert(vec![KeyChord::new(End,       none)], ClientAction::FindReplaceModeMoveCursorLineEnd);
+        find_replace.insert(vec![KeyChord::new(Enter,     none)], ClientAction::FindReplaceModeAccept);
+
+        // UndoHistory Mode
+        let undo_history = &mut keymap.undo_history;
+        undo_history.insert(vec![KeyChord::new(Esc,       none)], ClientAction::UndoHistoryModeExit);
+        undo_history.insert(vec![KeyChord::new(Enter,     none)], ClientAction::UndoHistoryModeAccept);
+        undo_history.insert(vec![KeyChord::new(Up,        none)], ClientAction::UndoHistoryModeSelectPrevious);
+        undo_history.insert(vec![KeyChord::new(Down,      none)], ClientAction::UndoHistoryModeSelectNext);
+
+        // Popup Mode
+        let popup = &mut keymap.popup;
+        popup.insert(vec![KeyChord::new(Esc,              none)], ClientAction::PopupModeExit);
+        popup.insert(vec![KeyChord::new(Enter,            none)], ClientAction::PopupModeExit);
+        popup.insert(vec![KeyChord::new(Char('q'),        none)], ClientAction::PopupModeExit);
+        popup.insert(vec![KeyChord::new(Up,               none)], ClientAction::PopupModeScrollUp);
+        popup.insert(vec![KeyChord::new(Down,             none)], ClientAction::PopupModeScrollDown);
+
+        // FilePicker Mode
+        let file_picker = &mut keymap.file_picker;
+        file_picker.insert(vec![KeyChord::new(Esc,        none)], ClientAction::FilePickerModeExit);
+        file_picker.insert(vec![KeyChord::new(Enter,      none)], ClientAction::FilePickerModeAccept);
+        file_picker.insert(vec![KeyChord::new(Backspace,  none)], ClientAction::FilePickerModeBackspace);
+        file_picker.insert(vec![KeyChord::new(Backspace,  ctrl)], ClientAction::FilePickerModeBackspaceWord);
+        file_picker.insert(vec![KeyChord::new(Up,         none)], ClientAction::FilePickerModeSelectPrevious);
+        file_picker.insert(vec![KeyChord::new(Down,       none)], ClientAction::FilePickerModeSelectNext);
+        file_picker.insert(vec![KeyChord::new(Char('p'),  ctrl)], ClientAction::FilePickerModeSelectPrevious);
+        file_picker.insert(vec![KeyChord::new(Char('n'),  ctrl)], ClientAction::FilePickerModeSelectNext);
+
+        // Command Mode
+        let command = &mut keymap.command;
+        command.insert(vec![KeyChord::new(Esc,          none)], ClientAction::CommandModeExit);
+        command.insert(vec![KeyChord::new(Enter,        none)], ClientAction::CommandModeAccept);
+        command.insert(vec![KeyChord::new(Backspace,    none)], ClientAction::CommandModeBackspace);
+        command.insert(vec![KeyChord::new(Delete,       none)], ClientAction::CommandModeDelete);
+        command.insert(vec![KeyChord::new(Right,        none)], ClientAction::CommandModeMoveCursorRight);
+        command.insert(vec![KeyChord::new(Left,         none)], ClientAction::CommandModeMoveCursorLeft);
+        command.insert(vec![KeyChord::new(Home,         none)], ClientAction::CommandModeMoveCursorLineStart);
+        command.insert(vec![KeyChord::new(Right,        ctrl)], ClientAction::CommandModeMoveCursorWordEnd);
+        command.insert(vec![KeyChord::new(Left,         ctrl)], ClientAction::CommandModeMoveCursorWordStart);
+        command.insert(vec![KeyChord::new(Backspace,    ctrl)], ClientAction::CommandModeBackspaceWord);
+        command.insert(vec![KeyChord::new(Delete,       ctrl)], ClientAction::CommandModeDeleteWord);
+        command.insert(vec![KeyChord::new(End,          none)], ClientAction::CommandModeMoveCursorLineEnd);
+
+        if profile == Profile::Modal{
+            keymap.add_modal_bindings();
+        }
+
+        keymap
+    }
+
+    /// vi like normal and visual mode bindings, layered over the modeless defaults
+    fn add_modal_bindings(&mut self){
+        fn bind(bindings: &mut HashMap<Vec<KeyChord>, ClientAction>, keys: &str, action: ClientAction){
+            bindings.insert(parse_key_sequence(keys).expect("default bindings should parse"), action);
+        }
+
+        self.insert.insert(vec![KeyChord::new(KeyCode::Esc, KeyModifiers::NONE)], ClientAction::SetModeNormal);
+
+        // motions, counts and operators behave the same in normal and visual mode
+        for bindings in [&mut self.normal, &mut self.visual]{
+            bind(bindings, "h",        ClientAction::NormalModeMotion(Motion::Left));
+            bind(bindings, "Left",     ClientAction::NormalModeMotion(Motion::Left));
+            bind(bindings, "j",        ClientAction::NormalModeMotion(Motion::Down));
+            bind(bindings, "Down",     ClientAction::NormalModeMotion(Motion::Down));
+            bind(bindings, "k",        ClientAction::NormalModeMotion(Motion::Up));
+            bind(bindings, "Up",       ClientAction::NormalModeMotion(Motion::Up));
+            bind(bindings, "l",        ClientAction::NormalModeMotion(Motion::Right));
+            bind(bindings, "Right",    ClientAction::NormalModeMotion(Motion::Right));
+            bind(bindings, "w",        ClientAction::NormalModeMotion(Motion::NextWordStart));
+            bind(bindings, "b",        ClientAction::NormalModeMotion(Motion::WordStart));
+            bind(bindings, "e",        ClientAction::NormalModeMotion(Motion::WordEnd));
+            bind(bindings, "$",        ClientAction::NormalModeMotion(Motion::LineEnd));
+            bind(bindings, "Home",     ClientAction::NormalModeMotion(Motion::LineStart));
+            bind(bindings, "End",      ClientAction::NormalModeMotion(Motion::LineEnd));
+            bind(bindings, "g g",      ClientAction::NormalModeMotion(Motion::DocumentStart));
+            bind(bindings, "G",        ClientAction::NormalModeMotion(Motion::DocumentEnd));
+            // 0 is a motion unless it continues a count
+            for digit in 0..=9{
+                bind(bindings, &digit.to_string(), ClientAction::NormalModeCount(digit));
+            }
+            bind(bindings, "d",        ClientAction::NormalModeOperator(Operator::Delete));
+            bind(bindings, "c",        ClientAction::NormalModeOperator(Operator::Change));
+            bind(bindings, "y",        ClientAction::NormalModeOperator(Operator::Yank));
+            bind(bindings, ":",        ClientAction::SetModeCommand);
+            bind(bindings, "\"",       ClientAction::SelectRegister);
+            bind(bindings, "Ctrl-s",   ClientAction::Save);
+            bind(bindings, "Ctrl-q",   ClientAction::Quit);
+            bind(bindings, "Ctrl-z",   ClientAction::Suspend);
+        }
+
+        // Normal Mode
+        let normal = &mut self.normal;
+        bind(normal, "Esc",            ClientAction::NormalModeCancel);
+        bind(normal, "i",              ClientAction::SetModeInsert);
+        bind(normal, "a",              ClientAction::NormalModeAppend);
+        bind(normal, "A",              ClientAction::NormalModeAppendLineEnd);
+        bind(normal, "I",              ClientAction::NormalModeInsertLineStart);
+        bind(normal, "o",              ClientAction::NormalModeOpenLineBelow);
+        bind(normal, "x",              ClientAction::NormalModeDeleteChar);
+        bind(normal, "v",              ClientAction::SetModeVisual);
+        bind(normal, "/",              ClientAction::SetModeFindReplace);
+        bind(normal, "p",              ClientAction::Paste);
+        bind(normal, "Ctrl-p",         ClientAction::PasteCycle);
+        bind(normal, "u",              ClientAction::Undo);
+        bind(normal, "Ctrl-r",         ClientAction::Redo);
+        bind(normal, "q",              ClientAction::ToggleMacroRecording);
+        bind(normal, "@",              ClientAction::PlayMacro);
+        bind(normal, "m",              ClientAction::ToggleBookmark);
+        bind(normal, "] c",            ClientAction::NextHunk);
+        bind(normal, "[ c",            ClientAction::PreviousHunk);
+        bind(normal, "g h",            ClientAction::PreviewHunk);
+        bind(normal, "Space f",        ClientAction::SetModeFilePicker);
+
+        // Visual Mode
+        let visual = &mut self.visual;
+        bind(visual, "Esc",            ClientAction::SetModeNormal);
+        bind(visual, "v",              ClientAction::SetModeNormal);
+        bind(visual, "x",              ClientAction::NormalModeOperator(Operator::Delete));
+    }
+
+    /// builds the default keymap for the profile the user's keymap file picks,
+    /// then applies the file's overrides
+    pub fn load() -> Result<Self, KeymapError>{
+        if let Some(path) = keymap_path(){
+            if path.exists(){
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|error| KeymapError::Io{path: path.clone(), error})?;
+                let file: KeymapFile = ron::from_str(&text)
+                    .map_err(|error| KeymapError::Parse{path: path.clone(), error})?;
+                let mut keymap = Self::with_profile(file.profile.unwrap_or(Profile::Modeless));
+                keymap.merge_file(file, &path)?;
+                return Ok(keymap);
+            }
+        }
+
+        Ok(Self::default())
+    }
+
+    fn merge_file(&mut self, file: KeymapFile, path: &Path) -> Result<(), KeymapError>{
+        if let Some(timeout) = file.timeout{
+            self.timeout = Duration::from_millis(timeout);
+        }
+
+        let sections = [
+            ("insert", file.insert, &mut self.insert),
+            ("warning", file.warning, &mut self.warning),
+            ("command", file.command, &mut self.command),
+            ("find_replace", file.find_replace, &mut self.find_replace),
+            ("goto", file.goto, &mut self.goto),
+            ("normal", file.normal, &mut self.normal),
+            ("visual", file.visual, &mut self.visual),
+            ("undo_history", file.undo_history, &mut self.undo_history),
+            ("popup", file.popup, &mut self.popup),
+            ("file_picker", file.file_picker, &mut self.file_picker),
+        ];
+        for (mode, bindings, target) in sections{
+            for (keys, action) in bindings{
+                let sequence = parse_key_sequence(&keys)
+                    .map_err(|error| KeymapError::InvalidKey{path: path.to_path_buf(), mode, error})?;
+                target.insert(sequence, action);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn bindings(&self, mode: Mode) -> &HashMap<Vec<KeyChord>, ClientAction>{
+        match mode{
+            Mode::Insert => &self.insert,
+            Mode::Warning(_) => &self.warning,
+            Mode::Command => &self.command,
+            Mode::FindReplace => &self.find_replace,
+            Mode::Goto => &self.goto,
+            Mode::Normal => &self.normal,
+            Mode::Visual => &self.visual,
+            Mode::UndoHistory => &self.undo_history,
+            Mode::Popup => &self.popup,
+            Mode::FilePicker => &self.file_picker,
+        }
+    }
+
+    /// the mode the editor rests in, returned to when leaving a utility mode
+    pub fn base_mode(&self) -> Mode{
+        match self.profile{
+            Profile::Modeless => Mode::Insert,
+            Profile::Modal => Mode::Normal,
+        }
+    }
+
+    /// false for modes the profile has no bindings for, which could not be left
+    pub fn has_mode(&self, mode: Mode) -> bool{
+        !matches!((self.profile, mode), (Profile::Modeless, Mode::Normal | Mode::Visual))
+    }
+
+    /// the bindings reachable from `prefix` in the given mode, as pairs of the
+    /// keys still to type and the name of the action they perform
+    pub fn help_entries(&self, mode: Mode, prefix: &[KeyChord]) -> Vec<(String, String)>{
+        let mut entries: Vec<(String, String)> = self.bindings(mode).iter()
+            .filter(|(sequence, _)| sequence.len() > prefix.len() && sequence.starts_with(prefix))
+            .map(|(sequence, action)| {
+                let keys = sequence[prefix.len()..].iter()
+                    .map(|chord| chord.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                (keys, format!("{action:?}"))
+            })
+            .collect();
+        entries.sort();
+
+        entries
+    }
+
+    pub fn timeout(&self) -> Duration{
+        self.timeout
+    }
+
+    /// true if `keys` is the start of some longer bound sequence
+    fn is_strict_prefix(&self, mode: Mode, keys: &[KeyChord]) -> bool{
+        self.bindings(mode).keys().any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys))
+    }
+
+    /// the action unbound keys perform. printable characters fall through to
+    /// the mode's text input action
+    fn fallback(mode: Mode, chord: KeyChord) -> ClientAction{
+        match (chord, mode){
+            (KeyChord{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c)}, Mode::Insert) => {ClientAction::InsertChar(c)}
+            (KeyChord{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c)}, Mode::Goto) => {ClientAction::GotoModeInsertChar(c)}
+            (KeyChord{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c)}, Mode::FindReplace) => {ClientAction::FindReplaceModeInsertChar(c)}
+            (KeyChord{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c)}, Mode::Command) => {ClientAction::CommandModeInsertChar(c)}
+            (KeyChord{modifiers: KeyModifiers::NONE, code: KeyCode::Char(c)}, Mode::FilePicker) => {ClientAction::FilePickerModeInsertChar(c)}
+            _ => {ClientAction::NoOp}
+        }
+    }
+
+    /// adds a key press to the pending sequence, and returns the action it
+    /// completes, if any. keys that could still grow into a longer binding stay pending
+    pub fn feed(&self, mode: Mode, pending: &mut Vec<KeyChord>, key_event: KeyEvent) -> Option<ClientAction>{
+        pending.push(KeyChord::from_key_event(key_event));
+        self.resolve(mode, pending, false)
+    }
+
+    /// resolves what is pending once the sequence timeout has elapsed
+    pub fn flush(&self, mode: Mode, pending: &mut Vec<KeyChord>) -> Option<ClientAction>{
+        self.resolve(mode, pending, true)
+    }
+
+    /// resolves the first action of the pending keys. the keys after it stay
+    /// pending, since the action may change the mode they should be read in
+    pub fn resolve(&self, mode: Mode, pending: &mut Vec<KeyChord>, force: bool) -> Option<ClientAction>{
+        if pending.is_empty() || (!force && self.is_strict_prefix(mode, pending)){
+            return None;
+        }
+
+        // the longest bound prefix wins. if nothing matches, the first key
+        // is handled on its own
+        let matched = (1..=pending.len()).rev()
+            .find_map(|len| self.bindings(mode).get(&pending[..len]).map(|action| (len, action.clone())));
+        match matched{
+            Some((len, action)) => {
+                pending.drain(..len);
+                Some(action)
+            }
+            None => Some(Self::fallback(mode, pending.remove(0)))
+        }
+    }
+}
+
+/// the keymap file section holding a mode's bindings
+pub fn section_name(mode: Mode) -> &'static str{
+    match mode{
+        Mode::Insert => "insert",
+        Mode::Warning(_) => "warning",
+        Mode::Command => "command",
+        Mode::FindReplace => "find_replace",
+        Mode::Goto => "goto",
+        Mode::Normal => "normal",
+        Mode::Visual => "visual",
+        Mode::UndoHistory => "undo_history",
+        Mode::Popup => "popup",
+        Mode::FilePicker => "file_picker",
+    }
+}
+
+/// the user's keymap file, `$XDG_CONFIG_HOME/nlo/keymap.ron` or `~/.config/nlo/keymap.ron`
+pub fn keymap_path() -> Option<PathBuf>{
+    config::config_dir().map(|dir| dir.join(KEYMAP_FILE_NAME))
+}
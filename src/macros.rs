@@ -0,0 +1,101 @@
+use crate::application::ClientAction;
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+
+
+const MACROS_FILE_NAME: &str = "macros.ron";
+
+
+
+/// recorded sequences of `ClientAction`s, stored by register name a-z. actions
+/// are recorded instead of keys, so macros keep working after keymap changes
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Macros{
+    saved: BTreeMap<char, Vec<ClientAction>>,
+    /// the register being recorded into, and the actions recorded so far
+    #[serde(skip)]
+    recording: Option<(char, Vec<ClientAction>)>,
+}
+impl Macros{
+    pub fn start_recording(&mut self, name: char){
+        self.recording = Some((name.to_ascii_lowercase(), Vec::new()));
+    }
+
+    /// stores the macro being recorded. returns false if nothing was recording
+    pub fn stop_recording(&mut self) -> bool{
+        match self.recording.take(){
+            Some((name, actions)) => {
+                self.saved.insert(name, actions);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// the register being recorded into, if any
+    pub fn recording(&self) -> Option<char>{
+        self.recording.as_ref().map(|(name, _)| *name)
+    }
+
+    /// adds an action to the macro being recorded, unless it controls macros itself
+    /// or depends on where the mouse was
+    pub fn record(&mut self, action: &ClientAction){
+        if let Some((_, actions)) = &mut self.recording{
+            if is_recordable(action){
+                actions.push(action.clone());
+            }
+        }
+    }
+
+    pub fn get(&self, name: char) -> Option<&[ClientAction]>{
+        self.saved.get(&name.to_ascii_lowercase()).map(|actions| actions.as_slice())
+    }
+
+    /// the macros saved by a previous session, if any
+    pub fn load() -> Result<Self, Box<dyn Error>>{
+        match macros_path(){
+            Some(path) if path.exists() => {
+                let text = std::fs::read_to_string(path)?;
+                Ok(ron::from_str(&text)?)
+            }
+            _ => Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>>{
+        if let Some(path) = macros_path(){
+            if let Some(dir) = path.parent(){
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_recordable(action: &ClientAction) -> bool{
+    !matches!(
+        action,
+        ClientAction::NoOp
+            | ClientAction::PlayMacro
+            | ClientAction::Resize(_, _)
+            | ClientAction::Suspend
+            | ClientAction::StartMacroRecording(_)
+            | ClientAction::ToggleMacroRecording
+            | ClientAction::ToggleKeyHelp
+            | ClientAction::MoveCursorTo(_, _)
+            | ClientAction::ExtendSelectionTo(_, _)
+            | ClientAction::SelectWordAt(_, _)
+            | ClientAction::UtilBarClick(_, _)
+    )
+}
+
+fn macros_path() -> Option<PathBuf>{
+    config::data_dir().map(|dir| dir.join(MACROS_FILE_NAME))
+}
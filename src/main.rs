@@ -1,42 +1,58 @@
 use crate::application::Application;
-use std::error::Error;
+use crate::error::ClientError;
+use std::process::ExitCode;
 
+mod annotations;
 mod application;
+mod clipboard;
+mod config;
+mod crash;
+mod diff;
+mod display;
+mod error;
+mod format;
+mod git;
+mod keymap;
+mod macros;
+mod messages;
+mod modal;
+mod picker;
+mod registers;
+mod shell;
+mod status;
+mod theme;
 mod ui;
+mod wrap;
 
 
 
 
-fn main() -> Result<(), Box<dyn Error>>{
-    let file_path = if std::env::args().count() > 1 && std::env::args().count() < 3{
-        std::env::args().nth(1).unwrap()
-    }else if std::env::args().count() > 2{
-        panic!("Too many arguments passed in.");
-    }else{
-        panic!("No file path provided.");
-    };
-    
-    let mut app = Application::new()?;
-    if let Err(e) = app.run(file_path){
-        app.restore_terminal()?;
-        println!("Encountered an error while running nlo code editor. error: {e}");
-        return Err(e);
+fn main() -> ExitCode{
+    match run(){
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("nlo: {e}");
+            ExitCode::FAILURE
+        }
     }
+}
 
-    //let mut args: Vec<String> = std::env::args().skip(1).collect();
-    //if let Some(file_path) = args.pop(){
-    //    if let Err(e) = app.run(file_path){
-    //        app.restore_terminal()?;
-    //        println!("Encountered an error while running nlo text editor client. error: {}", e);
-    //        return Err(e);
-    //    }
-    //}else{
-    //    app.restore_terminal()?;
-    //    println!("Could not open file. No file path provided.");
-    //    return Ok(());
-    //}
-
-    app.restore_terminal()?;
+fn run() -> Result<(), ClientError>{
+    let mut args = std::env::args().skip(1);
+    let file_path = match (args.next(), args.next()){
+        (Some(file_path), None) => file_path,
+        (Some(_), Some(_)) => return Err(ClientError::Usage("too many arguments. usage: nlo <file>".to_string())),
+        (None, _) => return Err(ClientError::Usage("no file path provided. usage: nlo <file>".to_string())),
+    };
     
-    Ok(())
+    crash::install_panic_hook();
+    crash::handle_termination_signals().map_err(ClientError::Terminal)?;
+
+    // setup errors are printed by main, before the terminal was ever taken over
+    let mut app = Application::new()?;
+    let result = app.run(file_path);
+    // the terminal is given back before any error is printed, so it prints to a normal screen
+    let restored = app.restore_terminal();
+    result?;
+    restored.map_err(ClientError::Terminal)
 }
@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+
+
+/// how many messages the log keeps before dropping the oldest
+const LOG_LIMIT: usize = 200;
+
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessageLevel{
+    Info,
+    Warning,
+    Error,
+}
+impl MessageLevel{
+    /// how long a message stays in the util bar. errors stay longest, as they
+    /// are the ones most likely to need reading twice
+    fn timeout(&self) -> Duration{
+        match self{
+            MessageLevel::Info => Duration::from_secs(3),
+            MessageLevel::Warning => Duration::from_secs(5),
+            MessageLevel::Error => Duration::from_secs(10),
+        }
+    }
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            MessageLevel::Info => "info",
+            MessageLevel::Warning => "warning",
+            MessageLevel::Error => "error",
+        }
+    }
+}
+
+pub struct Message{
+    pub level: MessageLevel,
+    pub text: String,
+    pub time: Instant,
+}
+
+/// messages for the user, shown one at a time in the util bar until they
+/// time out, and kept in a log that can be reviewed later
+#[derive(Default)]
+pub struct Messages{
+    log: VecDeque<Message>,
+    /// the newest message has been dismissed before timing out
+    dismissed: bool,
+}
+impl Messages{
+    pub fn push(&mut self, level: MessageLevel, text: impl Into<String>){
+        if self.log.len() >= LOG_LIMIT{
+            self.log.pop_front();
+        }
+        self.log.push_back(Message{level, text: text.into(), time: Instant::now()});
+        self.dismissed = false;
+    }
+
+    /// the message to show now, if the newest one has not timed out
+    pub fn current(&self) -> Option<&Message>{
+        let message = self.log.back()?;
+        (!self.dismissed && message.time.elapsed() < message.level.timeout()).then_some(message)
+    }
+
+    /// how long until the current message times out
+    pub fn time_left(&self) -> Option<Duration>{
+        let message = self.current()?;
+        Some(message.level.timeout().saturating_sub(message.time.elapsed()))
+    }
+
+    pub fn dismiss(&mut self){
+        self.dismissed = true;
+    }
+
+    /// every logged message, oldest first, as lines for the message log popup
+    pub fn listing(&self) -> Vec<String>{
+        self.log.iter()
+            .map(|message| {
+                let age = message.time.elapsed().as_secs();
+                format!("{:>5}s ago  {:<7}  {}", age, message.level.name(), message.text)
+            })
+            .collect()
+    }
+}
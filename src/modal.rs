@@ -0,0 +1,157 @@
+use nlo_text_editor_server::ServerAction;
+use serde::{Deserialize, Serialize};
+
+
+
+/// which editing model the keymap starts from
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Profile{
+    /// the default. typing inserts text, and commands live on modifier chords
+    Modeless,
+    /// vi like. keys are commands in normal mode, and text is typed in insert mode
+    Modal,
+}
+
+/// a cursor movement usable on its own, after an operator, or in visual mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Motion{
+    Left,
+    Right,
+    Up,
+    Down,
+    /// w
+    NextWordStart,
+    /// b
+    WordStart,
+    /// e
+    WordEnd,
+    /// 0
+    LineStart,
+    /// $
+    LineEnd,
+    /// gg
+    DocumentStart,
+    /// G
+    DocumentEnd,
+}
+impl Motion{
+    /// the server action performing this motion, either moving the cursor or
+    /// extending the selection from it
+    pub fn server_action(&self, extend_selection: bool) -> ServerAction{
+        match (self, extend_selection){
+            (Motion::Left, false) => ServerAction::MoveCursorLeft,
+            (Motion::Left, true) => ServerAction::ExtendSelectionLeft,
+            (Motion::Right, false) => ServerAction::MoveCursorRight,
+            (Motion::Right, true) => ServerAction::ExtendSelectionRight,
+            (Motion::Up, false) => ServerAction::MoveCursorUp,
+            (Motion::Up, true) => ServerAction::ExtendSelectionUp,
+            (Motion::Down, false) => ServerAction::MoveCursorDown,
+            (Motion::Down, true) => ServerAction::ExtendSelectionDown,
+            (Motion::NextWordStart, false) => ServerAction::MoveCursorNextWordStart,
+            (Motion::NextWordStart, true) => ServerAction::ExtendSelectionNextWordStart,
+            (Motion::WordStart, false) => ServerAction::MoveCursorWordStart,
+            (Motion::WordStart, true) => ServerAction::ExtendSelectionWordStart,
+            (Motion::WordEnd, false) => ServerAction::MoveCursorWordEnd,
+            (Motion::WordEnd, true) => ServerAction::ExtendSelectionWordEnd,
+            (Motion::LineStart, false) => ServerAction::MoveCursorLineStart,
+            (Motion::LineStart, true) => ServerAction::ExtendSelectionLineStart,
+            (Motion::LineEnd, false) => ServerAction::MoveCursorLineEnd,
+            (Motion::LineEnd, true) => ServerAction::ExtendSelectionLineEnd,
+            (Motion::DocumentStart, false) => ServerAction::MoveCursorDocumentStart,
+            (Motion::DocumentStart, true) => ServerAction::ExtendSelectionDocumentStart,
+            (Motion::DocumentEnd, false) => ServerAction::MoveCursorDocumentEnd,
+            (Motion::DocumentEnd, true) => ServerAction::ExtendSelectionDocumentEnd,
+        }
+    }
+
+    /// motions between lines, which an operator applies to whole lines, like dj
+    pub fn is_linewise(&self) -> bool{
+        matches!(self, Motion::Up | Motion::Down)
+    }
+
+    /// motions that already go as far as they can, so a count picks a line instead of repeating
+    pub fn count_is_line_number(&self) -> bool{
+        matches!(self, Motion::DocumentStart | Motion::DocumentEnd)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Operator{
+    /// d
+    Delete,
+    /// c
+    Change,
+    /// y
+    Yank,
+}
+impl Operator{
+    fn symbol(&self) -> char{
+        match self{
+            Operator::Delete => 'd',
+            Operator::Change => 'c',
+            Operator::Yank => 'y',
+        }
+    }
+}
+
+/// the count and operator typed so far in normal mode, like the "3d" of "3d2w"
+#[derive(Default)]
+pub struct ModalState{
+    count: Option<usize>,
+    operator: Option<(Operator, Option<usize>)>,
+}
+impl ModalState{
+    pub fn push_digit(&mut self, digit: u32){
+        let count = self.count.unwrap_or(0);
+        self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+    }
+
+    pub fn has_count(&self) -> bool{
+        self.count.is_some()
+    }
+
+    pub fn set_operator(&mut self, operator: Operator){
+        self.operator = Some((operator, self.count.take()));
+    }
+
+    pub fn operator(&self) -> Option<Operator>{
+        self.operator.map(|(operator, _)| operator)
+    }
+
+    /// takes the pending operator and the combined count typed before and
+    /// after it. the count is None if none was typed at all
+    pub fn take(&mut self) -> (Option<Operator>, Option<usize>){
+        let count = self.count.take();
+        match self.operator.take(){
+            Some((operator, operator_count)) => {
+                let count = match (operator_count, count){
+                    (Some(a), Some(b)) => Some(a.saturating_mul(b)),
+                    (a, b) => a.or(b)
+                };
+                (Some(operator), count)
+            }
+            None => (None, count)
+        }
+    }
+
+    pub fn clear(&mut self){
+        self.count = None;
+        self.operator = None;
+    }
+
+    /// the pending count and operator, for display in the status bar
+    pub fn pending_text(&self) -> String{
+        let mut text = String::new();
+        if let Some((operator, operator_count)) = self.operator{
+            if let Some(count) = operator_count{
+                text.push_str(&count.to_string());
+            }
+            text.push(operator.symbol());
+        }
+        if let Some(count) = self.count{
+            text.push_str(&count.to_string());
+        }
+
+        text
+    }
+}
@@ -0,0 +1,317 @@
+use crate::git;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+
+
+/// the most files listed when walking a directory outside of git
+const WALK_LIMIT: usize = 20_000;
+/// how much of the selected file is read for the preview
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+
+
+/// a fuzzy searchable list of the files in a project, with a preview of the
+/// selected one
+pub struct FilePicker{
+    root: PathBuf,
+    /// paths relative to the root
+    files: Vec<String>,
+    query: String,
+    /// indices into files, best match first
+    matches: Vec<usize>,
+    /// index into matches
+    selected: usize,
+    /// the selected file's first lines
+    preview: Vec<String>,
+    /// the file the preview was read from, so it is only read again when the selection moves to another
+    preview_path: Option<PathBuf>,
+}
+impl FilePicker{
+    /// lists the project holding `directory`: the git repository's files when
+    /// in one, so .gitignore is respected, or else the directory's own files
+    pub fn new(directory: &Path) -> Self{
+        let (root, files) = match git::toplevel(directory).and_then(|root| Some((git::project_files(&root)?, root))){
+            Some((files, root)) => (root, files),
+            None => (directory.to_path_buf(), walk(directory))
+        };
+        let mut picker = Self{
+            root,
+            files,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            preview: Vec::new(),
+            preview_path: None,
+        };
+        picker.update_matches();
+
+        picker
+    }
+
+    pub fn root(&self) -> &Path{
+        &self.root
+    }
+    pub fn query(&self) -> &str{
+        &self.query
+    }
+    pub fn file_count(&self) -> usize{
+        self.files.len()
+    }
+    /// the matching paths, best first
+    pub fn matches(&self) -> impl Iterator<Item = &str>{
+        self.matches.iter().map(|index| self.files[*index].as_str())
+    }
+    pub fn match_count(&self) -> usize{
+        self.matches.len()
+    }
+    pub fn selected(&self) -> usize{
+        self.selected
+    }
+    pub fn preview(&self) -> &[String]{
+        &self.preview
+    }
+
+    /// the selected file's full path
+    pub fn selected_path(&self) -> Option<PathBuf>{
+        let index = self.matches.get(self.selected)?;
+        Some(self.root.join(&self.files[*index]))
+    }
+
+    pub fn insert_char(&mut self, c: char){
+        self.query.push(c);
+        self.update_matches();
+    }
+    pub fn backspace(&mut self){
+        self.query.pop();
+        self.update_matches();
+    }
+    /// removes the query's last word, and the separator before it
+    pub fn backspace_word(&mut self){
+        let trimmed = self.query.trim_end_matches(is_separator);
+        let length = trimmed.trim_end_matches(|c| !is_separator(c)).len();
+        self.query.truncate(length);
+        self.update_matches();
+    }
+
+    pub fn select_next(&mut self){
+        if self.selected + 1 < self.matches.len(){
+            self.selected += 1;
+            self.update_preview();
+        }
+    }
+    pub fn select_previous(&mut self){
+        if self.selected > 0{
+            self.selected -= 1;
+            self.update_preview();
+        }
+    }
+
+    fn update_matches(&mut self){
+        let mut scored: Vec<(i64, usize)> = self.files.iter().enumerate()
+            .filter_map(|(index, file)| Some((fuzzy_score(&self.query, file)?, index)))
+            .collect();
+        // ties go to the shorter path, then alphabetical order
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.cmp(a_score)
+                .then(self.files[*a].len().cmp(&self.files[*b].len()))
+                .then(self.files[*a].cmp(&self.files[*b]))
+        });
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+        self.update_preview();
+    }
+
+    fn update_preview(&mut self){
+        let path = self.selected_path();
+        if path == self.preview_path{
+            return;
+        }
+        self.preview = match &path{
+            Some(path) => read_preview(path),
+            None => Vec::new()
+        };
+        self.preview_path = path;
+    }
+}
+
+/// how well `query` matches `candidate`, or None if its characters don't all
+/// appear in order. matches at the start of words, in a row, or in the file
+/// name score higher. the query is case sensitive only if it has uppercase letters
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64>{
+    if query.is_empty(){
+        return Some(0);
+    }
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| if case_sensitive{c}else{c.to_ascii_lowercase()};
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let original: Vec<char> = candidate.chars().collect();
+    let chars: Vec<char> = original.iter().copied().map(fold).collect();
+    let file_name_start = original.iter().rposition(|c| *c == '/').map(|index| index + 1).unwrap_or(0);
+
+    // a greedy match from each place the query could start, keeping the best
+    let best = (0..chars.len())
+        .filter(|start| chars[*start] == query[0])
+        .filter_map(|start| {
+            let mut score = 0i64;
+            let mut position = start;
+            let mut previous: Option<usize> = None;
+            for (n, q) in query.iter().enumerate(){
+                let found = if n == 0{start}else{(position..chars.len()).find(|index| chars[*index] == *q)?};
+                score += 1;
+                if previous.is_some_and(|previous| previous + 1 == found){
+                    score += 5;
+                }else if previous.is_some(){
+                    // skipped characters cost a little, but never more than a word start earns
+                    score -= (found - position).min(3) as i64;
+                }
+                let word_start = found == 0
+                    || is_separator(original[found - 1])
+                    || (original[found - 1].is_lowercase() && original[found].is_uppercase());
+                if word_start{
+                    score += 8;
+                }
+                if found >= file_name_start{
+                    score += 2;
+                }
+                previous = Some(found);
+                position = found + 1;
+            }
+            Some(score)
+        })
+        .max()?;
+
+    // among equal matches, shorter paths are more likely what was meant
+    Some(best - chars.len() as i64 / 16)
+}
+
+fn is_separator(c: char) -> bool{
+    matches!(c, '/' | '\\' | '_' | '-' | '.' | ' ')
+}
+
+/// the files below a directory, relative to it. hidden files and directories
+/// are skipped, as are symlinked directories, which could loop
+fn walk(directory: &Path) -> Vec<String>{
+    let mut files = Vec::new();
+    let mut pending = vec![directory.to_path_buf()];
+    'walk: while let Some(current) = pending.pop(){
+        let Ok(entries) = std::fs::read_dir(&current) else{
+            continue;
+        };
+        let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries{
+            if entry.file_name().to_string_lossy().starts_with('.'){
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else{
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir(){
+                pending.push(path);
+            }else if path.is_file(){
+                if let Ok(relative) = path.strip_prefix(directory){
+                    files.push(relative.to_string_lossy().into_owned());
+                }
+                if files.len() >= WALK_LIMIT{
+                    break 'walk;
+                }
+            }
+        }
+    }
+    files.sort();
+
+    files
+}
+
+/// the start of a file as lines, or a note saying why it can't be shown
+fn read_preview(path: &Path) -> Vec<String>{
+    let mut bytes = Vec::new();
+    let read = std::fs::File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut bytes));
+    if let Err(error) = read{
+        return vec![format!("could not read file: {error}")];
+    }
+    if bytes.contains(&0){
+        return vec!["binary file".to_string()];
+    }
+
+    String::from_utf8_lossy(&bytes).lines().map(str::to_string).collect()
+}
+
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn picker(root: &Path, files: &[&str]) -> FilePicker{
+        let mut picker = FilePicker{
+            root: root.to_path_buf(),
+            files: files.iter().map(|file| file.to_string()).collect(),
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            preview: Vec::new(),
+            preview_path: None,
+        };
+        picker.update_matches();
+
+        picker
+    }
+
+    fn score(query: &str, candidate: &str) -> i64{
+        fuzzy_score(query, candidate).unwrap()
+    }
+
+    #[test]
+    fn word_starts_score_higher(){
+        assert!(score("fb", "foo_bar.rs") > score("fb", "afxbx.rs"));
+        assert!(score("fb", "fooBar.rs") > score("fb", "afxbx.rs"));
+    }
+
+    #[test]
+    fn consecutive_runs_score_higher(){
+        assert!(score("abc", "xabcx") > score("abc", "xaxbxcx"));
+    }
+
+    #[test]
+    fn file_names_score_higher_than_directories(){
+        assert!(score("main", "src/main.rs") > score("main", "main/src.rs"));
+    }
+
+    #[test]
+    fn uppercase_makes_the_query_case_sensitive(){
+        assert!(fuzzy_score("readme", "README.md").is_some());
+        assert!(fuzzy_score("README", "readme.md").is_none());
+        assert!(fuzzy_score("Rd", "readme.md").is_none());
+        assert!(fuzzy_score("xyz", "abc").is_none());
+        assert_eq!(fuzzy_score("", "abc"), Some(0));
+    }
+
+    #[test]
+    fn ties_go_to_shorter_then_alphabetical_paths(){
+        let mut picker = picker(Path::new("/nonexistent"), &["b/ab.rs", "a/ab.rs", "ab.rs", "other.txt"]);
+        picker.insert_char('a');
+        picker.insert_char('b');
+        assert_eq!(picker.matches().collect::<Vec<_>>(), vec!["ab.rs", "a/ab.rs", "b/ab.rs"]);
+    }
+
+    #[test]
+    fn preview_is_read_again_only_for_another_file(){
+        let root = std::env::temp_dir().join(format!("nlo-picker-test-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("a.rs"), "one").unwrap();
+        std::fs::write(root.join("b.rs"), "two").unwrap();
+        let mut picker = picker(&root, &["a.rs", "b.rs"]);
+        assert_eq!(picker.preview(), ["one"]);
+        // still the same file selected, so typing doesn't read it again
+        std::fs::write(root.join("a.rs"), "changed").unwrap();
+        picker.insert_char('a');
+        assert_eq!(picker.preview(), ["one"]);
+        picker.backspace();
+        picker.select_next();
+        assert_eq!(picker.preview(), ["two"]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::path::PathBuf;
+
+
+
+const REGISTERS_FILE_NAME: &str = "registers.ron";
+/// how many copies and cuts the kill ring remembers
+const KILL_RING_SIZE: usize = 32;
+
+
+
+/// named registers a-z, plus a kill ring of recent copies and cuts
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Registers{
+    named: BTreeMap<char, String>,
+    /// newest first
+    kill_ring: VecDeque<String>,
+    /// the kill ring entry the last paste inserted, while pastes are being cycled
+    #[serde(skip)]
+    cycle_index: Option<usize>,
+}
+impl Registers{
+    /// true for the names `set` and `get` accept
+    pub fn is_valid_name(name: char) -> bool{
+        name.is_ascii_alphabetic()
+    }
+
+    /// stores text in a named register. like vi, an uppercase name appends to
+    /// the lowercase register instead of replacing it
+    pub fn set(&mut self, name: char, text: &str){
+        let register = self.named.entry(name.to_ascii_lowercase()).or_default();
+        if name.is_ascii_uppercase(){
+            register.push_str(text);
+        }else{
+            *register = text.to_string();
+        }
+    }
+
+    pub fn get(&self, name: char) -> Option<&str>{
+        self.named.get(&name.to_ascii_lowercase()).map(|text| text.as_str())
+    }
+
+    pub fn push_kill(&mut self, text: &str){
+        if self.kill_ring.front().map(|newest| newest.as_str()) == Some(text){
+            return;
+        }
+        self.kill_ring.push_front(text.to_string());
+        self.kill_ring.truncate(KILL_RING_SIZE);
+    }
+
+    /// starts a paste cycle after pasting text. if the text came from outside
+    /// the editor, the first cycle step goes to the newest kill ring entry
+    pub fn start_cycle(&mut self, pasted: &str){
+        self.cycle_index = match self.kill_ring.front(){
+            Some(newest) if newest == pasted => Some(0),
+            Some(_) => Some(self.kill_ring.len() - 1),
+            None => None
+        };
+    }
+
+    pub fn end_cycle(&mut self){
+        self.cycle_index = None;
+    }
+
+    /// steps a paste cycle to the next older kill ring entry, wrapping around.
+    /// returns None if no paste cycle is in progress
+    pub fn cycle(&mut self) -> Option<&str>{
+        let index = self.cycle_index?;
+        if self.kill_ring.is_empty(){
+            return None;
+        }
+        let index = (index + 1) % self.kill_ring.len();
+        self.cycle_index = Some(index);
+        self.kill_ring.get(index).map(|text| text.as_str())
+    }
+
+    /// one line per register and kill ring entry, for the registers listing
+    pub fn listing(&self) -> Vec<String>{
+        fn preview(text: &str) -> String{
+            text.escape_debug().to_string()
+        }
+
+        let mut lines = Vec::new();
+        for (name, text) in &self.named{
+            lines.push(format!("\"{name}  {}", preview(text)));
+        }
+        if !self.named.is_empty() && !self.kill_ring.is_empty(){
+            lines.push(String::new());
+        }
+        for (index, text) in self.kill_ring.iter().enumerate(){
+            lines.push(format!("{index:>2}  {}", preview(text)));
+        }
+        if lines.is_empty(){
+            lines.push("no registers set".to_string());
+        }
+
+        lines
+    }
+
+    /// the registers saved by a previous session, if any
+    pub fn load() -> Result<Self, Box<dyn Error>>{
+        match registers_path(){
+            Some(path) if path.exists() => {
+                let text = std::fs::read_to_string(path)?;
+                Ok(ron::from_str(&text)?)
+            }
+            _ => Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>>{
+        if let Some(path) = registers_path(){
+            if let Some(dir) = path.parent(){
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, ron::to_string(self)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn registers_path() -> Option<PathBuf>{
+    config::data_dir().map(|dir| dir.join(REGISTERS_FILE_NAME))
+}
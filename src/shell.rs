@@ -0,0 +1,119 @@
+use std::io::{Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+
+
+/// used when $SHELL is unset
+const DEFAULT_SHELL: &str = "sh";
+/// how long a command may run before it is killed. the editor can't redraw
+/// or take input meanwhile
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// the most a command may print on each of stdout and stderr
+const OUTPUT_LIMIT: usize = 32 * 1024 * 1024;
+/// how often a running command is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+
+
+pub struct ShellOutput{
+    pub stdout: String,
+    pub stderr: String,
+    pub status: ExitStatus,
+}
+impl ShellOutput{
+    /// a description of how the command failed, if it did
+    pub fn failure(&self) -> Option<String>{
+        match self.status.code(){
+            Some(0) => None,
+            Some(code) => Some(format!("exited with status {code}")),
+            None => Some("was killed by a signal".to_string())
+        }
+    }
+}
+
+/// runs a command line through the user's shell in the working directory,
+/// feeding it `input` on stdin. the command never sees the terminal, since
+/// the editor holds it in raw mode. it is killed if it runs past TIMEOUT or
+/// prints more than OUTPUT_LIMIT
+pub fn run(command: &str, input: Option<&str>) -> std::io::Result<ShellOutput>{
+    let shell = std::env::var("SHELL").ok().filter(|shell| !shell.is_empty());
+    let mut child = Command::new(shell.as_deref().unwrap_or(DEFAULT_SHELL))
+        .arg("-c").arg(command)
+        .stdin(if input.is_some(){Stdio::piped()}else{Stdio::null()})
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // written from another thread, so a command that fills its stdout pipe
+    // before reading all its input can't deadlock against us
+    let writer = match (child.stdin.take(), input){
+        (Some(mut stdin), Some(input)) => {
+            let input = input.to_string();
+            Some(std::thread::spawn(move || {
+                // a command that exits without reading its input is not an error here
+                let _ = stdin.write_all(input.as_bytes());
+            }))
+        }
+        _ => None
+    };
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let stdout = child.stdout.take().map(|pipe| read_limited(pipe, overflowed.clone()));
+    let stderr = child.stderr.take().map(|pipe| read_limited(pipe, overflowed.clone()));
+
+    // polled rather than waited on, so a command that hangs, or leaves a
+    // background process holding its output open, can be given up on
+    let deadline = Instant::now() + TIMEOUT;
+    let mut exited = None;
+    let status = loop{
+        if exited.is_none(){
+            exited = child.try_wait()?;
+        }
+        let read = [&stdout, &stderr].into_iter().flatten().all(|reader| reader.is_finished());
+        // checked before exiting, since a command cut off mid output usually exits right away
+        let failure = if overflowed.load(Ordering::SeqCst){
+            std::io::Error::other(format!("printed more than {} MiB", OUTPUT_LIMIT / 1024 / 1024))
+        }else if let (Some(status), true) = (exited, read){
+            break status;
+        }else if Instant::now() >= deadline{
+            std::io::Error::new(std::io::ErrorKind::TimedOut, format!("still running after {} seconds", TIMEOUT.as_secs()))
+        }else{
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        // the command may have exited already, which makes killing it fail harmlessly
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(failure);
+    };
+    if let Some(writer) = writer{
+        let _ = writer.join();
+    }
+
+    let text = |reader: Option<JoinHandle<Vec<u8>>>| {
+        let bytes = reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+    Ok(ShellOutput{
+        stdout: text(stdout),
+        stderr: text(stderr),
+        status,
+    })
+}
+
+/// reads a pipe to its end on another thread. past OUTPUT_LIMIT it stops
+/// and sets `overflowed`, so the command can be killed
+fn read_limited(pipe: impl Read + Send + 'static, overflowed: Arc<AtomicBool>) -> JoinHandle<Vec<u8>>{
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        // a read error ends the output like its end would
+        let _ = pipe.take(OUTPUT_LIMIT as u64 + 1).read_to_end(&mut bytes);
+        if bytes.len() > OUTPUT_LIMIT{
+            overflowed.store(true, Ordering::SeqCst);
+        }
+        bytes
+    })
+}
@@ -0,0 +1,99 @@
+use serde::Deserialize;
+use std::path::Path;
+
+
+
+/// the pieces the status bar can be built from
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum StatusSegment{
+    /// the current mode's name
+    Mode,
+    /// [Modified] while the document has unsaved changes
+    Modified,
+    /// the file name the server reports
+    FileName,
+    /// the file's path relative to the working directory, or absolute outside it
+    FilePath,
+    AbsolutePath,
+    /// 1 based line:column of the cursor
+    Position,
+    /// how far through the document the cursor line is
+    Percent,
+    Encoding,
+    /// LF, CRLF or CR, as found in the file when opened
+    LineEnding,
+    /// the language guessed from the file extension
+    Language,
+    Cursors,
+    GitBranch,
+    /// error and warning counts
+    Diagnostics,
+    /// a partially typed key sequence, and macro recording
+    PendingKeys,
+}
+
+/// the segments drawn at each end and the middle of the status bar
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusBarLayout{
+    pub left: Vec<StatusSegment>,
+    pub center: Vec<StatusSegment>,
+    pub right: Vec<StatusSegment>,
+}
+impl Default for StatusBarLayout{
+    fn default() -> Self{
+        Self{
+            left: vec![StatusSegment::Mode, StatusSegment::Modified, StatusSegment::FileName],
+            center: Vec::new(),
+            right: vec![StatusSegment::PendingKeys, StatusSegment::Position],
+        }
+    }
+}
+
+/// the line ending a file mostly uses, judged by its first line break
+pub fn line_ending(bytes: &[u8]) -> &'static str{
+    match bytes.iter().position(|byte| *byte == b'\n' || *byte == b'\r'){
+        Some(index) if bytes[index] == b'\n' => "LF",
+        Some(index) if bytes.get(index + 1) == Some(&b'\n') => "CRLF",
+        Some(_) => "CR",
+        // a file without line breaks is written with the platform's
+        None => if cfg!(windows){"CRLF"}else{"LF"}
+    }
+}
+
+/// a language name for the file's extension, or its name for extensionless files like Makefile
+pub fn language(path: &Path) -> Option<&'static str>{
+    let file_name = path.file_name()?.to_str()?;
+    match file_name{
+        "Makefile" | "makefile" | "GNUmakefile" => return Some("make"),
+        "Dockerfile" => return Some("dockerfile"),
+        "CMakeLists.txt" => return Some("cmake"),
+        _ => {}
+    }
+    Some(
+        match path.extension()?.to_str()?{
+            "rs" => "rust",
+            "c" | "h" => "c",
+            "cc" | "cpp" | "cxx" | "hpp" | "hh" => "c++",
+            "go" => "go",
+            "py" => "python",
+            "js" | "mjs" | "cjs" => "javascript",
+            "ts" => "typescript",
+            "tsx" | "jsx" => "react",
+            "java" => "java",
+            "rb" => "ruby",
+            "sh" | "bash" => "shell",
+            "lua" => "lua",
+            "zig" => "zig",
+            "html" | "htm" => "html",
+            "css" => "css",
+            "json" => "json",
+            "toml" => "toml",
+            "yaml" | "yml" => "yaml",
+            "ron" => "ron",
+            "md" => "markdown",
+            "txt" => "text",
+            _ => return None
+        }
+    )
+}
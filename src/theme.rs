@@ -0,0 +1,352 @@
+use crate::config;
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+
+
+pub const DEFAULT_THEME: &str = "dark";
+/// themes built into the client. a user theme file with the same name takes precedence
+const BUILTIN_THEMES: [(&str, &str); 2] = [
+    ("dark", include_str!("../themes/dark.ron")),
+    ("light", include_str!("../themes/light.ron")),
+];
+const THEME_DIR_NAME: &str = "themes";
+const THEME_FILE_EXTENSION: &str = "ron";
+/// the 16 ansi colors as xterm draws them, for picking the nearest one to an rgb color
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+
+
+#[derive(Debug)]
+pub enum ThemeError{
+    NotFound{name: String},
+    Io{path: PathBuf, error: std::io::Error},
+    Parse{name: String, error: ron::error::SpannedError},
+    InvalidColor{name: String, scope: String, color: String},
+}
+impl fmt::Display for ThemeError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            ThemeError::NotFound{name} => {
+                write!(f, "no theme named {}", name)
+            }
+            ThemeError::Io{path, error} => {
+                write!(f, "could not read theme {}: {}", path.display(), error)
+            }
+            ThemeError::Parse{name, error} => {
+                write!(f, "could not parse theme {}: {}", name, error)
+            }
+            ThemeError::InvalidColor{name, scope, color} => {
+                write!(f, "invalid color {:?} for {} in theme {}", color, scope, name)
+            }
+        }
+    }
+}
+impl Error for ThemeError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self{
+            ThemeError::Io{error, ..} => Some(error),
+            ThemeError::Parse{error, ..} => Some(error),
+            ThemeError::NotFound{..} | ThemeError::InvalidColor{..} => None,
+        }
+    }
+}
+
+/// how many colors the terminal can show. theme colors beyond that are
+/// replaced by the nearest color it has
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum ColorSupport{
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+impl ColorSupport{
+    /// guesses from the environment, the same way most terminal programs do
+    pub fn detect() -> Self{
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit"{
+            ColorSupport::TrueColor
+        }else if term.contains("256color"){
+            ColorSupport::Ansi256
+        }else{
+            ColorSupport::Ansi16
+        }
+    }
+}
+
+/// the on disk layout of a scope in a theme file. colors are names like
+/// "red" or "dark_gray", "#rrggbb" hex, or a 256 color palette index
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ScopeStyle{
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    reversed: bool,
+}
+
+/// the on disk layout of a theme file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile{
+    /// scope name, like "ui.gutter" or "syntax.keyword" -> its style
+    scopes: HashMap<String, ScopeStyle>,
+}
+
+/// named styles for everything the client draws. scopes are dotted names, and
+/// a scope missing from the theme takes the style of its nearest parent, so
+/// "ui.statusbar.modified" falls back to "ui.statusbar"
+pub struct Theme{
+    name: String,
+    scopes: HashMap<String, Style>,
+}
+impl Theme{
+    /// reads a theme from the user's theme directory, or a built in one, with
+    /// colors reduced to what the terminal supports
+    pub fn load(name: &str, color_support: ColorSupport) -> Result<Self, ThemeError>{
+        let user_path = config::config_dir()
+            .map(|dir| dir.join(THEME_DIR_NAME).join(name).with_extension(THEME_FILE_EXTENSION));
+        let text = match user_path{
+            Some(path) if path.exists() => {
+                std::fs::read_to_string(&path)
+                    .map_err(|error| ThemeError::Io{path, error})?
+            }
+            _ => {
+                BUILTIN_THEMES.iter()
+                    .find(|(builtin, _)| *builtin == name)
+                    .map(|(_, text)| text.to_string())
+                    .ok_or_else(|| ThemeError::NotFound{name: name.to_string()})?
+            }
+        };
+        // colors can be written without Some(..), as scopes leave most of them out
+        let file: ThemeFile = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(&text)
+            .map_err(|error| ThemeError::Parse{name: name.to_string(), error})?;
+
+        let mut scopes = HashMap::new();
+        for (scope, scope_style) in file.scopes{
+            let color = |color: &Option<String>| match color{
+                Some(text) => match parse_color(text){
+                    Some(color) => Ok(Some(reduce_color(color, color_support))),
+                    None => Err(ThemeError::InvalidColor{name: name.to_string(), scope: scope.clone(), color: text.clone()})
+                }
+                None => Ok(None)
+            };
+            let mut style = Style::default();
+            if let Some(fg) = color(&scope_style.fg)?{style = style.fg(fg);}
+            if let Some(bg) = color(&scope_style.bg)?{style = style.bg(bg);}
+            if scope_style.bold{style = style.add_modifier(Modifier::BOLD);}
+            if scope_style.italic{style = style.add_modifier(Modifier::ITALIC);}
+            if scope_style.underlined{style = style.add_modifier(Modifier::UNDERLINED);}
+            if scope_style.reversed{style = style.add_modifier(Modifier::REVERSED);}
+            scopes.insert(scope, style);
+        }
+
+        Ok(Self{name: name.to_string(), scopes})
+    }
+
+    pub fn name(&self) -> &str{
+        &self.name
+    }
+
+    /// the style of a scope, or of its nearest parent scope the theme defines
+    pub fn style(&self, scope: &str) -> Style{
+        let mut scope = scope;
+        loop{
+            if let Some(style) = self.scopes.get(scope){
+                return *style;
+            }
+            match scope.rfind('.'){
+                Some(index) => scope = &scope[..index],
+                None => return Style::default()
+            }
+        }
+    }
+}
+
+/// the names of every theme that can be loaded, built in and user defined
+pub fn available_themes() -> Vec<String>{
+    let mut names: Vec<String> = BUILTIN_THEMES.iter().map(|(name, _)| name.to_string()).collect();
+    if let Some(Ok(entries)) = config::config_dir().map(|dir| std::fs::read_dir(dir.join(THEME_DIR_NAME))){
+        for path in entries.flatten().map(|entry| entry.path()){
+            if path.extension().is_some_and(|extension| extension == THEME_FILE_EXTENSION){
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()){
+                    names.push(name.to_string());
+                }
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+
+    names
+}
+
+fn parse_color(text: &str) -> Option<Color>{
+    if let Some(hex) = text.strip_prefix('#'){
+        if hex.len() != 6{
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(index) = text.parse::<u8>(){
+        return Some(Color::Indexed(index));
+    }
+    Some(
+        match text{
+            "reset" => Color::Reset,
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "gray" => Color::Gray,
+            "dark_gray" => Color::DarkGray,
+            "light_red" => Color::LightRed,
+            "light_green" => Color::LightGreen,
+            "light_yellow" => Color::LightYellow,
+            "light_blue" => Color::LightBlue,
+            "light_magenta" => Color::LightMagenta,
+            "light_cyan" => Color::LightCyan,
+            "white" => Color::White,
+            _ => return None
+        }
+    )
+}
+
+/// the color closest to `color` that a terminal with `color_support` can show
+fn reduce_color(color: Color, color_support: ColorSupport) -> Color{
+    match (color, color_support){
+        (Color::Rgb(r, g, b), ColorSupport::Ansi256) => Color::Indexed(nearest_256((r, g, b))),
+        (Color::Rgb(r, g, b), ColorSupport::Ansi16) => nearest_16((r, g, b)),
+        // the first 16 palette entries are the ansi colors themselves
+        (Color::Indexed(index), ColorSupport::Ansi16) if index >= 16 => nearest_16(indexed_rgb(index)),
+        (color, _) => color
+    }
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color{
+    ANSI_COLORS.iter()
+        .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+/// the nearest entry of the 6x6x6 color cube or the gray ramp of the 256 color palette
+fn nearest_256(rgb: (u8, u8, u8)) -> u8{
+    (16..=255u8)
+        .min_by_key(|index| distance(rgb, indexed_rgb(*index)))
+        .unwrap_or(16)
+}
+
+/// the rgb value of a 256 color palette entry past the 16 ansi colors
+fn indexed_rgb(index: u8) -> (u8, u8, u8){
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index{
+        0..=15 => ANSI_COLORS[index as usize].1,
+        16..=231 => {
+            let index = index - 16;
+            (CUBE_LEVELS[(index / 36) as usize], CUBE_LEVELS[(index / 6 % 6) as usize], CUBE_LEVELS[(index % 6) as usize])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32{
+    let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn colors_parse_from_hex_indices_and_names(){
+        assert_eq!(parse_color("#ff8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(parse_color("#FF8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(parse_color("42"), Some(Color::Indexed(42)));
+        assert_eq!(parse_color("light_blue"), Some(Color::LightBlue));
+        assert_eq!(parse_color("reset"), Some(Color::Reset));
+    }
+
+    #[test]
+    fn malformed_colors_are_rejected(){
+        for text in ["#fff", "#ff80000", "#gg0000", "#ééé", "256", "-1", "purple", ""]{
+            assert_eq!(parse_color(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn palette_indices_map_to_their_rgb(){
+        assert_eq!(indexed_rgb(1), (205, 0, 0));
+        assert_eq!(indexed_rgb(16), (0, 0, 0));
+        assert_eq!(indexed_rgb(196), (255, 0, 0));
+        assert_eq!(indexed_rgb(67), (95, 135, 175));
+        assert_eq!(indexed_rgb(231), (255, 255, 255));
+        assert_eq!(indexed_rgb(232), (8, 8, 8));
+        assert_eq!(indexed_rgb(255), (238, 238, 238));
+    }
+
+    #[test]
+    fn nearest_256_picks_the_closest_cube_or_gray(){
+        assert_eq!(nearest_256((255, 0, 0)), 196);
+        assert_eq!(nearest_256((95, 135, 175)), 67);
+        assert_eq!(nearest_256((128, 128, 128)), 244);
+        assert_eq!(nearest_256((0, 0, 0)), 16);
+        // nearer cube level 95 than 0, and far from any gray
+        assert_eq!(nearest_256((60, 0, 0)), 52);
+        // dark colors can sit closer to the gray ramp than to the cube
+        assert_eq!(nearest_256((40, 0, 0)), 233);
+        // every palette color maps back to itself, or an entry of the same rgb
+        for index in 16..=255{
+            assert_eq!(indexed_rgb(nearest_256(indexed_rgb(index))), indexed_rgb(index));
+        }
+    }
+
+    #[test]
+    fn colors_are_reduced_to_what_the_terminal_shows(){
+        let orange = Color::Rgb(255, 128, 0);
+        assert_eq!(reduce_color(orange, ColorSupport::TrueColor), orange);
+        assert_eq!(reduce_color(orange, ColorSupport::Ansi256), Color::Indexed(208));
+        assert_eq!(reduce_color(Color::Rgb(250, 5, 5), ColorSupport::Ansi16), Color::LightRed);
+        assert_eq!(reduce_color(Color::Indexed(196), ColorSupport::Ansi16), Color::LightRed);
+        // ansi colors and palette entries a 256 color terminal has are kept
+        assert_eq!(reduce_color(Color::Indexed(3), ColorSupport::Ansi16), Color::Indexed(3));
+        assert_eq!(reduce_color(Color::Indexed(196), ColorSupport::Ansi256), Color::Indexed(196));
+        assert_eq!(reduce_color(Color::Blue, ColorSupport::Ansi16), Color::Blue);
+    }
+}
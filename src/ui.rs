@@ -1,13 +1,26 @@
 use crate::application::{Mode, WarningKind};
-use nlo_text_editor_server::Position;
-use std::error::Error;
+use crate::keymap;
+use crate::annotations::Annotations;
+use crate::theme::Theme;
+use crate::annotations::AnnotationKind;
+use crate::messages::Messages;
+use crate::picker::FilePicker;
+use crate::status::{self, StatusBarLayout, StatusSegment};
+use crate::display::{self, GlyphKind};
+use crate::wrap::{self, VisualRow};
+use nlo_text_editor_server::{Position, UndoEntry};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 use ratatui::Terminal;
 use ratatui::layout::Rect;
 use ratatui::prelude::CrosstermBackend;
-use ratatui::widgets::Paragraph;
-use ratatui::style::{Style, Color, Stylize};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::text::{Line, Span};
+use ratatui::style::Style;
 use ratatui::layout::{Alignment, Direction, Layout, Constraint};
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 
 
@@ -16,9 +29,26 @@ const FIND_PROMPT: &str = " Find: ";
 const REPLACE_PROMPT: &str = " Replace: ";
 const MODIFIED_INDICATOR: &str = "[Modified]";
 const COMMAND_PROMPT: &str = " Command: ";
+/// shown in the line number gutter beside the rows of a soft wrapped line after its first
+const WRAP_MARKER: &str = "↪";
+const SPACE_MARKER: char = '·';
+const TAB_MARKER: char = '→';
+const LINE_END_MARKER: char = '¬';
+const INDENT_GUIDE: char = '│';
 
 
 
+/// what the line number gutter shows
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LineNumberMode{
+    Absolute,
+    /// distance from the cursor line
+    Relative,
+    /// absolute on the cursor line, relative elsewhere
+    Hybrid,
+    None,
+}
+
 pub struct UtilBar{
     text: String,
     text_is_valid: bool,
@@ -59,7 +89,12 @@ impl UtilBar{
     }
 
     pub fn insert_char(&mut self, char: char){
-        self.text.insert(self.cursor_position.into(), char);
+        // cursor_position counts graphemes, but String::insert takes a byte index
+        let byte_index = self.text.grapheme_indices(true)
+            .nth(self.cursor_position as usize)
+            .map(|(index, _)| index)
+            .unwrap_or(self.text.len());
+        self.text.insert(byte_index, char);
         self.move_cursor_right();
     }
 
@@ -90,6 +125,11 @@ impl UtilBar{
         }
     }
 
+    /// moves the cursor to a grapheme index, clamped to the end of the text
+    pub fn move_cursor_to(&mut self, position: u16){
+        self.cursor_position = position.min(self.text[..].graphemes(true).count() as u16);
+    }
+
     pub fn move_cursor_home(&mut self){
         self.cursor_position = 0;
         self.offset = 0;
@@ -99,6 +139,68 @@ impl UtilBar{
         self.cursor_position = self.text[..].graphemes(true).count() as u16;
     }
 
+    /// grapheme index ranges of the words in the text. unicode word bounds that
+    /// hold no alphanumeric characters, like whitespace and punctuation, are skipped
+    fn words(&self) -> Vec<(u16, u16)>{
+        let mut words = Vec::new();
+        let mut start = 0;
+        for segment in self.text.split_word_bounds(){
+            let end = start + segment.graphemes(true).count() as u16;
+            if segment.chars().any(|c| c.is_alphanumeric()){
+                words.push((start, end));
+            }
+            start = end;
+        }
+
+        words
+    }
+
+    /// where a word start motion from the cursor lands
+    fn word_start_position(&self) -> u16{
+        self.words().iter().rev()
+            .map(|&(start, _)| start)
+            .find(|&start| start < self.cursor_position)
+            .unwrap_or(0)
+    }
+
+    /// where a word end motion from the cursor lands
+    fn word_end_position(&self) -> u16{
+        self.words().iter()
+            .map(|&(_, end)| end)
+            .find(|&end| end > self.cursor_position)
+            .unwrap_or(self.text[..].graphemes(true).count() as u16)
+    }
+
+    /// removes the graphemes in start..end
+    fn remove_range(&mut self, start: u16, end: u16){
+        let mut result = String::new();
+        for (index, grapheme) in self.text[..].graphemes(true).enumerate(){
+            if index < start as usize || index >= end as usize{
+                result.push_str(grapheme);
+            }
+        }
+        self.text = result;
+    }
+
+    pub fn move_cursor_word_start(&mut self){
+        self.cursor_position = self.word_start_position();
+    }
+
+    pub fn move_cursor_word_end(&mut self){
+        self.cursor_position = self.word_end_position();
+    }
+
+    pub fn delete_word(&mut self){
+        let end = self.word_end_position();
+        self.remove_range(self.cursor_position, end);
+    }
+
+    pub fn backspace_word(&mut self){
+        let start = self.word_start_position();
+        self.remove_range(start, self.cursor_position);
+        self.cursor_position = start;
+    }
+
     pub fn offset(&self) -> u16{
         self.offset
     }
@@ -116,20 +218,71 @@ impl UtilBar{
     }
 }
 
+/// the document's edit history, as browsed in undo history mode
+#[derive(Default)]
+pub struct UndoHistory{
+    entries: Vec<UndoEntry>,
+    /// the entry the document is currently at
+    current: usize,
+    selected: usize,
+}
+impl UndoHistory{
+    pub fn set_entries(&mut self, entries: Vec<UndoEntry>, current: usize){
+        self.entries = entries;
+        self.current = current;
+        self.selected = current;
+    }
+
+    pub fn selected(&self) -> usize{
+        self.selected
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.entries.is_empty()
+    }
+
+    pub fn select_next(&mut self){
+        if self.selected + 1 < self.entries.len(){
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self){
+        self.selected = self.selected.saturating_sub(1);
+    }
+}
+
 pub struct UserInterface{
     terminal_size: Rect,
     display_line_numbers: bool,
+    line_number_mode: LineNumberMode,
+    display_sign_column: bool,
+    /// markers from diagnostics, version control, bookmarks and the like, shown in the sign column
+    annotations: Annotations,
+    theme: Theme,
+    /// info, warning and error messages for the util bar
+    messages: Messages,
     display_status_bar: bool,
+    display_key_help: bool,
+    /// wrap long lines at the document rect width instead of scrolling horizontally
+    soft_wrap: bool,
+    wrap_at_words: bool,
+    /// cells between tab stops
+    tab_width: usize,
+    /// draw markers for spaces, tabs and line ends, and indentation guides
+    show_whitespace: bool,
+    highlight_trailing_whitespace: bool,
     /// the area of the terminal filled by an open document
     document_rect: Rect,
     /// the area of the terminal filled by line numbers
     line_number_rect: Rect,
-    /// the area of the status bar for indicating file modification status
-    status_bar_modified_indicator_rect: Rect,
-    /// the area of the status bar for indicating file name
-    status_bar_file_name_rect: Rect,
-    /// the area of the status bar for indicating cursor position within document
-    status_bar_cursor_position_rect: Rect,
+    /// the area of the terminal between line numbers and document, for annotation signs
+    sign_column_rect: Rect,
+    /// the areas of the status bar for left aligned, centered and right aligned segments
+    status_bar_left_rect: Rect,
+    status_bar_center_rect: Rect,
+    status_bar_right_rect: Rect,
+    status_bar_layout: StatusBarLayout,
     /// the area of the util bar for primary utility prompts
     util_bar_prompt_rect: Rect,
     /// the area of the util bar for primary user input
@@ -145,22 +298,57 @@ pub struct UserInterface{
     text_in_view: String,
     line_numbers_in_view: String,
     client_cursor_position: Option<Position>,
+    /// how many cursors the server last reported
+    cursor_count: usize,
     document_length: usize,
     document_modified_status: bool,
     document_file_name: Option<String>,
+    document_path: Option<PathBuf>,
+    /// the line ending found in the file when opened
+    line_ending: Option<&'static str>,
+    git_branch: Option<String>,
     document_cursor_position: Option<Position>,
+    /// the document line at the top of the view, inferred from the cursor.
+    /// only used when the server sends no line numbers
+    view_top: usize,
+    /// the grapheme each line in view starts from when the server has scrolled
+    /// right, inferred from the cursor like view_top. 0 while soft wrapping
+    view_left: usize,
+    pending_keys: String,
+    undo_history: UndoHistory,
+    /// title and lines of a read only popup, and how far it is scrolled
+    popup: Option<(String, Vec<String>)>,
+    popup_offset: u16,
+    /// key sequence and action name pairs shown in the key help popup
+    key_help: Option<Vec<(String, String)>>,
+    file_picker: Option<FilePicker>,
+    /// the area of the file picker's query, where the cursor is drawn
+    file_picker_query_rect: Rect,
 }
 impl UserInterface{
-    pub fn new(terminal_size: Rect) -> Self{
+    pub fn new(terminal_size: Rect, theme: Theme) -> Self{
         Self{
             terminal_size,
             display_line_numbers: true,
+            line_number_mode: LineNumberMode::Absolute,
+            display_sign_column: true,
+            annotations: Annotations::default(),
+            theme,
+            messages: Messages::default(),
             display_status_bar: true,
+            display_key_help: false,
+            soft_wrap: false,
+            wrap_at_words: false,
+            tab_width: display::DEFAULT_TAB_WIDTH,
+            show_whitespace: false,
+            highlight_trailing_whitespace: false,
             document_rect: Rect::default(),
             line_number_rect: Rect::default(),
-            status_bar_modified_indicator_rect: Rect::default(),
-            status_bar_file_name_rect: Rect::default(),
-            status_bar_cursor_position_rect: Rect::default(),
+            sign_column_rect: Rect::default(),
+            status_bar_left_rect: Rect::default(),
+            status_bar_center_rect: Rect::default(),
+            status_bar_right_rect: Rect::default(),
+            status_bar_layout: StatusBarLayout::default(),
             util_bar_prompt_rect: Rect::default(),
             util_bar_rect: Rect::default(),
             util_bar_alternate_prompt_rect: Rect::default(),
@@ -171,10 +359,23 @@ impl UserInterface{
             text_in_view: String::new(),
             line_numbers_in_view: String::new(),
             client_cursor_position: None,
+            cursor_count: 0,
             document_length: 0,
             document_modified_status: false,
             document_file_name: None,
+            document_path: None,
+            line_ending: None,
+            git_branch: None,
             document_cursor_position: None,
+            view_top: 0,
+            view_left: 0,
+            pending_keys: String::new(),
+            key_help: None,
+            undo_history: UndoHistory::default(),
+            popup: None,
+            popup_offset: 0,
+            file_picker: None,
+            file_picker_query_rect: Rect::default(),
         }
     }
     pub fn document_modified(&self) -> bool{
@@ -190,14 +391,155 @@ impl UserInterface{
     pub fn set_file_name(&mut self, file_name: Option<String>){
         self.document_file_name = file_name;
     }
+    pub fn document_path(&self) -> Option<&Path>{
+        self.document_path.as_deref()
+    }
+    pub fn set_document_path(&mut self, path: Option<PathBuf>){
+        self.document_path = path;
+    }
+    pub fn set_line_ending(&mut self, line_ending: Option<&'static str>){
+        self.line_ending = line_ending;
+    }
+    pub fn set_git_branch(&mut self, git_branch: Option<String>){
+        self.git_branch = git_branch;
+    }
+    pub fn set_status_bar_layout(&mut self, status_bar_layout: StatusBarLayout){
+        self.status_bar_layout = status_bar_layout;
+    }
     pub fn document_length(&self) -> usize{
         self.document_length
     }
     pub fn set_document_length(&mut self, document_length: usize){
         self.document_length = document_length;
     }
+    pub fn document_cursor_position(&self) -> Option<Position>{
+        self.document_cursor_position
+    }
     pub fn set_document_cursor_position(&mut self, cursor_position: Position){
         self.document_cursor_position = Some(cursor_position);
+        // the view's corner is only known while the cursor is in view, so keep the last one otherwise
+        if let Some(client_cursor_position) = self.client_cursor_position{
+            self.view_top = cursor_position.y().saturating_sub(client_cursor_position.y());
+            self.view_left = cursor_position.x().saturating_sub(client_cursor_position.x());
+        }
+    }
+
+    /// follows a horizontal scroll the server made. with the cursor still in
+    /// view the offset was already read from it. otherwise it is moved by the
+    /// amount asked for, which is off only when the server stopped at an edge
+    pub fn scrolled_horizontally(&mut self, right: bool, amount: usize){
+        if self.client_cursor_position.is_some() || self.soft_wrap{
+            return;
+        }
+        self.view_left = if right{
+            self.view_left.saturating_add(amount)
+        }else{
+            self.view_left.saturating_sub(amount)
+        };
+    }
+
+    /// the cell the cursor's grapheme starts at, counted from the view's left edge
+    fn cursor_cell(&self) -> Option<usize>{
+        let cursor = self.client_cursor_position?;
+        let line = self.text_in_view.lines().nth(cursor.y()).unwrap_or("");
+        Some(display::cell_of(line, cursor.x(), self.view_left, self.tab_width))
+    }
+
+    /// translates a terminal cell to a document (x, y) position. cells outside
+    /// the document rect give None, unless `clamp` pulls them to its nearest edge
+    pub fn document_position(&self, column: u16, row: u16, clamp: bool) -> Option<(usize, usize)>{
+        let rect = self.document_rect;
+        if rect.width == 0 || rect.height == 0{
+            return None;
+        }
+        let inside = column >= rect.x && column < rect.x + rect.width
+            && row >= rect.y && row < rect.y + rect.height;
+        if !inside && !clamp{
+            return None;
+        }
+        let view_x = column.clamp(rect.x, rect.x + rect.width - 1) - rect.x;
+        let view_y = row.clamp(rect.y, rect.y + rect.height - 1) - rect.y;
+
+        if self.soft_wrap{
+            let rows = self.wrapped_rows();
+            let lines: Vec<&str> = self.text_in_view.lines().collect();
+            // rows below the last line pick the end of the view's last row
+            let index = (self.wrap_top(&rows) + view_y as usize).min(rows.len().saturating_sub(1));
+            let (x, y) = wrap::position_at(&rows, &lines, index, view_x as usize, self.tab_width)?;
+            return Some((x, self.view_line(y)));
+        }
+
+        let line = self.text_in_view.lines().nth(view_y as usize).unwrap_or("");
+        let x = display::column_at(line, view_x as usize, self.view_left, self.tab_width);
+
+        Some((self.view_left + x, self.view_line(view_y as usize)))
+    }
+
+    /// the rows of the view's lines when soft wrapped at the document rect width
+    fn wrapped_rows(&self) -> Vec<VisualRow>{
+        let cursor = self.client_cursor_position.map(|cursor| (cursor.x(), cursor.y()));
+        wrap::wrap_text(&self.text_in_view, self.document_rect.width as usize, self.wrap_at_words, self.tab_width, cursor)
+    }
+
+    /// the first wrapped row shown. the server scrolls by whole lines, so when
+    /// wrapping pushes the cursor below the document rect, rows are skipped until it fits
+    fn wrap_top(&self, rows: &[VisualRow]) -> usize{
+        let height = self.document_rect.height as usize;
+        match self.wrapped_cursor_cell(rows){
+            Some((row, _)) if row >= height => row + 1 - height,
+            _ => 0
+        }
+    }
+
+    /// the wrapped row and cell column of the cursor
+    fn wrapped_cursor_cell(&self, rows: &[VisualRow]) -> Option<(usize, usize)>{
+        let cursor = self.client_cursor_position?;
+        let lines: Vec<&str> = self.text_in_view.lines().collect();
+        wrap::cursor_cell(rows, &lines, cursor.x(), cursor.y(), self.tab_width)
+    }
+
+    /// the document (x, y) position one wrapped row above or below the cursor,
+    /// keeping its cell column. None if that row is outside the lines in view
+    pub fn visual_line_move(&self, up: bool) -> Option<(usize, usize)>{
+        let rows = self.wrapped_rows();
+        let lines: Vec<&str> = self.text_in_view.lines().collect();
+        let (row, column) = self.wrapped_cursor_cell(&rows)?;
+        let target = if up{row.checked_sub(1)?}else{row + 1};
+        let (x, y) = wrap::position_at(&rows, &lines, target, column, self.tab_width)?;
+
+        Some((x, self.view_line(y)))
+    }
+
+    /// moves the cursor of whichever util bar contains the clicked cell.
+    /// returns false if the click missed both
+    pub fn click_util_bar(&mut self, column: u16, row: u16, mode: Mode) -> bool{
+        let contains = |rect: Rect| column >= rect.x && column < rect.x + rect.width && row >= rect.y && row < rect.y + rect.height;
+        match mode{
+            Mode::Goto | Mode::Command | Mode::FindReplace if contains(self.util_bar_rect) => {
+                let position = Self::util_bar_position(&self.util_bar, column - self.util_bar_rect.x, self.tab_width);
+                self.util_bar.move_cursor_to(position);
+                self.util_bar_alternate_focused = false;
+                true
+            }
+            Mode::FindReplace if contains(self.util_bar_alternate_rect) => {
+                let position = Self::util_bar_position(&self.util_bar_alternate, column - self.util_bar_alternate_rect.x, self.tab_width);
+                self.util_bar_alternate.move_cursor_to(position);
+                self.util_bar_alternate_focused = true;
+                true
+            }
+            _ => false
+        }
+    }
+
+    /// the grapheme index drawn `column` cells into a util bar. the bar is
+    /// scrolled by cells, and wide graphemes take more than one
+    fn util_bar_position(util_bar: &UtilBar, column: u16, tab_width: usize) -> u16{
+        let cell = util_bar.offset() as usize + column as usize;
+        display::column_at(util_bar.text(), cell, 0, tab_width).min(u16::MAX as usize) as u16
+    }
+
+    pub fn set_pending_keys(&mut self, pending_keys: String){
+        self.pending_keys = pending_keys;
     }
 
     pub fn document_rect(&self) -> Rect{
@@ -210,6 +552,22 @@ impl UserInterface{
     pub fn set_display_line_numbers(&mut self, display_line_numbers: bool){
         self.display_line_numbers = display_line_numbers
     }
+    pub fn set_line_number_mode(&mut self, line_number_mode: LineNumberMode){
+        self.line_number_mode = line_number_mode
+    }
+    pub fn set_display_sign_column(&mut self, display_sign_column: bool){
+        self.display_sign_column = display_sign_column
+    }
+    pub fn annotations(&self) -> &Annotations{
+        &self.annotations
+    }
+    pub fn annotations_mut(&mut self) -> &mut Annotations{
+        &mut self.annotations
+    }
+    /// true if the line number gutter takes up any space
+    fn gutter_visible(&self) -> bool{
+        self.display_line_numbers && self.line_number_mode != LineNumberMode::None
+    }
 
     pub fn display_status_bar(&self) -> bool{
         self.display_status_bar
@@ -218,6 +576,87 @@ impl UserInterface{
         self.display_status_bar = display_status_bar
     }
 
+    pub fn soft_wrap(&self) -> bool{
+        self.soft_wrap
+    }
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool){
+        self.soft_wrap = soft_wrap;
+    }
+    pub fn set_wrap_at_words(&mut self, wrap_at_words: bool){
+        self.wrap_at_words = wrap_at_words;
+    }
+    pub fn messages(&self) -> &Messages{
+        &self.messages
+    }
+    pub fn messages_mut(&mut self) -> &mut Messages{
+        &mut self.messages
+    }
+    pub fn theme(&self) -> &Theme{
+        &self.theme
+    }
+    pub fn set_theme(&mut self, theme: Theme){
+        self.theme = theme;
+    }
+    pub fn set_tab_width(&mut self, tab_width: usize){
+        self.tab_width = tab_width.max(1);
+    }
+    pub fn show_whitespace(&self) -> bool{
+        self.show_whitespace
+    }
+    pub fn set_show_whitespace(&mut self, show_whitespace: bool){
+        self.show_whitespace = show_whitespace;
+    }
+    pub fn highlight_trailing_whitespace(&self) -> bool{
+        self.highlight_trailing_whitespace
+    }
+    pub fn set_highlight_trailing_whitespace(&mut self, highlight_trailing_whitespace: bool){
+        self.highlight_trailing_whitespace = highlight_trailing_whitespace;
+    }
+
+    pub fn display_key_help(&self) -> bool{
+        self.display_key_help
+    }
+    pub fn set_display_key_help(&mut self, display_key_help: bool){
+        self.display_key_help = display_key_help
+    }
+
+    pub fn set_key_help(&mut self, key_help: Option<Vec<(String, String)>>){
+        self.key_help = key_help;
+    }
+
+    pub fn undo_history(&self) -> &UndoHistory{
+        &self.undo_history
+    }
+    pub fn undo_history_mut(&mut self) -> &mut UndoHistory{
+        &mut self.undo_history
+    }
+
+    pub fn file_picker(&self) -> Option<&FilePicker>{
+        self.file_picker.as_ref()
+    }
+    pub fn file_picker_mut(&mut self) -> Option<&mut FilePicker>{
+        self.file_picker.as_mut()
+    }
+    pub fn set_file_picker(&mut self, file_picker: Option<FilePicker>){
+        self.file_picker = file_picker;
+    }
+
+    pub fn set_popup(&mut self, popup: Option<(String, Vec<String>)>){
+        self.popup = popup;
+        self.popup_offset = 0;
+    }
+
+    pub fn scroll_popup_down(&mut self){
+        let line_count = self.popup.as_ref().map(|(_, lines)| lines.len()).unwrap_or(0);
+        if (self.popup_offset as usize) + 1 < line_count{
+            self.popup_offset += 1;
+        }
+    }
+
+    pub fn scroll_popup_up(&mut self){
+        self.popup_offset = self.popup_offset.saturating_sub(1);
+    }
+
     pub fn util_bar(&self) -> &UtilBar{
         &self.util_bar
     }
@@ -245,6 +684,7 @@ impl UserInterface{
 
     pub fn set_client_cursor_position(&mut self, positions: Vec<Position>){
         //self.client_cursor_position = position;
+        self.cursor_count = positions.len();
         if !positions.is_empty(){
             self.client_cursor_position = Some(*positions.last().unwrap());
         }else{
@@ -271,7 +711,12 @@ impl UserInterface{
                             | Mode::FindReplace
                             | Mode::Command => 1,
                             
-                            Mode::Insert => if self.display_status_bar{1}else{0}
+                            Mode::Insert
+                            | Mode::Normal
+                            | Mode::Visual
+                            | Mode::UndoHistory
+                            | Mode::Popup
+                            | Mode::FilePicker => if self.display_status_bar || self.messages.current().is_some(){1}else{0}
                         }
                     )
                 ]
@@ -283,41 +728,31 @@ impl UserInterface{
             .direction(Direction::Horizontal)
             .constraints(
                 vec![
-                    // line number left padding
-                    //Constraint::Length(if self.display_line_numbers{1}else{0}),
                     // line number rect width
-                    Constraint::Length(
-                        if self.display_line_numbers{
-                            count_digits(self.document_length)
-                        }else{0}
-                    ),
-                    // line number right padding
-                    Constraint::Length(if self.display_line_numbers{1}else{0}),
+                    // relative numbers are sized like absolute ones, since they grow past
+                    // the view height once the cursor is scrolled out of view
+                    Constraint::Length(if self.gutter_visible(){count_digits(self.line_count())}else{0}),
+                    // sign column width
+                    Constraint::Length(if self.display_sign_column{1}else{0}),
+                    // gutter right padding
+                    Constraint::Length(if self.gutter_visible() || self.display_sign_column{1}else{0}),
                     // document rect width
                     Constraint::Min(5)
                 ]
             )
             .split(viewport_rect[0]);
 
-        // layout of status bar rect (modified_indicator/file_name/cursor_position)
+        // layout of status bar rect (left/center/right segments)
         let status_bar_rect = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(
                 vec![
-                    // modified indicator width
-                    Constraint::Max(
-                        if self.document_modified_status{
-                            MODIFIED_INDICATOR.len() as u16
-                        }else{0}
-                    ),
-                    // file_name width
-                    Constraint::Max(
-                        if let Some(file_name) = &self.document_file_name{
-                            file_name.len() as u16
-                        }else{0}
-                    ),
-                    // cursor position indicator width
-                    Constraint::Min(0)
+                    // left segments width
+                    Constraint::Length(self.status_bar_line(&self.status_bar_layout.left, mode).width() as u16),
+                    // center segments fill what is left between the ends
+                    Constraint::Min(0),
+                    // right segments width
+                    Constraint::Length(self.status_bar_line(&self.status_bar_layout.right, mode).width() as u16),
                 ]
             )
             .split(viewport_rect[1]);
@@ -339,7 +774,7 @@ impl UserInterface{
                     // util bar rect width
                     Constraint::Length(
                         match mode{
-                            Mode::Warning(_) | Mode::Insert => viewport_rect[2].width,
+                            Mode::Warning(_) | Mode::Insert | Mode::Normal | Mode::Visual | Mode::UndoHistory | Mode::Popup | Mode::FilePicker => viewport_rect[2].width,
                             Mode::Goto => viewport_rect[2].width - GOTO_PROMPT.len() as u16,
                             Mode::Command => viewport_rect[2].width - COMMAND_PROMPT.len() as u16,                            
                             Mode::FindReplace => (viewport_rect[2].width / 2) - FIND_PROMPT.len() as u16,
@@ -366,11 +801,12 @@ impl UserInterface{
             .split(viewport_rect[2]);
 
         self.line_number_rect = document_and_line_num_rect[0];
-        // dont have to set line num right padding(document_and_line_num_rect[1])
-        self.document_rect = document_and_line_num_rect[2];
-        self.status_bar_modified_indicator_rect = status_bar_rect[0];
-        self.status_bar_file_name_rect = status_bar_rect[1];
-        self.status_bar_cursor_position_rect = status_bar_rect[2];
+        self.sign_column_rect = document_and_line_num_rect[1];
+        // dont have to set gutter right padding(document_and_line_num_rect[2])
+        self.document_rect = document_and_line_num_rect[3];
+        self.status_bar_left_rect = status_bar_rect[0];
+        self.status_bar_center_rect = status_bar_rect[1];
+        self.status_bar_right_rect = status_bar_rect[2];
         self.util_bar_prompt_rect = util_rect[0];
         self.util_bar_rect = util_rect[1];
         self.util_bar_alternate_prompt_rect = util_rect[2];
@@ -388,59 +824,276 @@ impl UserInterface{
         }
     }
 
-    //TODO: find out why we have double padding to left of line nums
     pub fn line_number_widget(&self) -> Paragraph<'static>{
-        Paragraph::new(self.line_numbers_in_view.clone())
-            .style(Style::default().fg(Color::Rgb(100, 100, 100)))
+        let numbers = self.gutter_numbers();
+        let number_line = |(number, current): &(String, bool)| {
+            if *current{
+                Line::from(Span::styled(number.clone(), self.theme.style("ui.gutter.current")))
+            }else{
+                Line::from(number.clone())
+            }
+        };
+        let lines: Vec<Line> = if self.soft_wrap{
+            // only a line's first row is numbered
+            let rows = self.wrapped_rows();
+            rows.iter()
+                .skip(self.wrap_top(&rows))
+                .map(|row| match numbers.get(row.line){
+                    Some(_) if row.is_continuation() => Line::from(WRAP_MARKER),
+                    Some(number) => number_line(number),
+                    None => Line::default()
+                })
+                .collect()
+        }else{
+            numbers.iter().map(number_line).collect()
+        };
+        Paragraph::new(lines)
+            .style(self.theme.style("ui.gutter"))
             .alignment(Alignment::Right)
     }
 
+    /// the gutter text of each view line in the current line number mode, and
+    /// whether it is the cursor's line
+    fn gutter_numbers(&self) -> Vec<(String, bool)>{
+        let cursor_line = self.document_cursor_position.map(|position| position.y());
+        self.view_document_lines().into_iter()
+            .map(|line| {
+                let current = Some(line) == cursor_line;
+                let distance = line.abs_diff(cursor_line.unwrap_or(line));
+                let text = match self.line_number_mode{
+                    LineNumberMode::Relative => distance.to_string(),
+                    LineNumberMode::Hybrid if !current => distance.to_string(),
+                    LineNumberMode::Absolute | LineNumberMode::Hybrid | LineNumberMode::None => (line + 1).to_string()
+                };
+                (text, current)
+            })
+            .collect()
+    }
+
+    /// the document's length as last reported, or the last line in view if
+    /// edits have made the document longer since
+    fn line_count(&self) -> usize{
+        let last_in_view = self.view_document_lines().last().map_or(0, |line| line + 1);
+
+        self.document_length.max(last_in_view)
+    }
+
+    /// the 0 based document line of each view line. the server's line numbers
+    /// are trusted over the view offset, which is stale while the cursor is out of view
+    fn view_document_lines(&self) -> Vec<usize>{
+        let numbers: Vec<&str> = self.line_numbers_in_view.lines().collect();
+        (0..self.text_in_view.lines().count())
+            .map(|index| match numbers.get(index).and_then(|number| number.trim().parse::<usize>().ok()){
+                // the server numbers lines from 1
+                Some(number) => number.saturating_sub(1),
+                None => self.view_top + index
+            })
+            .collect()
+    }
+
+    /// the document line shown on a view row. rows below the last line count
+    /// on from it, so a click there can be clamped by the server
+    fn view_line(&self, row: usize) -> usize{
+        let document_lines = self.view_document_lines();
+        match (document_lines.get(row), document_lines.last()){
+            (Some(&line), _) => line,
+            (None, Some(&last)) => last + row + 1 - document_lines.len(),
+            (None, None) => self.view_top + row
+        }
+    }
+
+    pub fn sign_column_widget(&self) -> Paragraph<'static>{
+        let sign_line = |line: usize| match self.annotations.sign(line){
+            Some((sign, scope)) => Line::from(Span::styled(sign.to_string(), self.theme.style(scope))),
+            None => Line::default()
+        };
+        let document_lines = self.view_document_lines();
+        let lines: Vec<Line> = if self.soft_wrap{
+            let rows = self.wrapped_rows();
+            rows.iter()
+                .skip(self.wrap_top(&rows))
+                .map(|row| match document_lines.get(row.line){
+                    Some(&line) if !row.is_continuation() => sign_line(line),
+                    _ => Line::default()
+                })
+                .collect()
+        }else{
+            document_lines.into_iter().map(sign_line).collect()
+        };
+
+        Paragraph::new(lines)
+    }
+
     pub fn document_widget(&self) -> Paragraph<'static>{
-            Paragraph::new(self.text_in_view.clone())
+        let lines: Vec<&str> = self.text_in_view.lines().collect();
+        let indents = self.guide_indents(&lines);
+        let indent_width = display::detect_indent_width(&lines, self.tab_width);
+        let trailing: Vec<Option<usize>> = lines.iter().map(|line| display::trailing_whitespace_start(line)).collect();
+        let text: Vec<Line> = if self.soft_wrap{
+            let rows = self.wrapped_rows();
+            rows.iter().enumerate()
+                .skip(self.wrap_top(&rows))
+                .map(|(index, row)| {
+                    let marks = LineMarks{
+                        trailing_whitespace: trailing[row.line].and_then(|start| (start < row.end).then(|| start.saturating_sub(row.start))),
+                        indent_cells: if row.is_continuation(){0}else{indents[row.line]},
+                        line_end: rows.get(index + 1).is_none_or(|next| next.line != row.line),
+                    };
+                    self.document_line(&wrap::row_text(lines.get(row.line).copied().unwrap_or(""), row), 0, indent_width, marks)
+                })
+                .collect()
+        }else{
+            // the server sends each line from grapheme view_left on. what is left
+            // of that is unknown, so tab stops are placed as if each hidden
+            // grapheme took one cell. drawing, the cursor and clicks all count
+            // from the same start, so they agree with each other
+            lines.iter().enumerate()
+                .map(|(index, line)| {
+                    // the server clips lines to the view's width, so a line that
+                    // fills it may go on past the right edge. only a shorter one
+                    // is known to end, and to have its trailing whitespace in view
+                    let ends_in_view = line.graphemes(true).count() < self.document_rect.width as usize;
+                    let marks = LineMarks{
+                        trailing_whitespace: trailing[index].filter(|_| ends_in_view),
+                        indent_cells: indents[index],
+                        line_end: ends_in_view,
+                    };
+                    self.document_line(line, self.view_left, indent_width, marks)
+                })
+                .collect()
+        };
+
+        Paragraph::new(text)
     }
 
-    pub fn status_bar_modified_indicator_widget(&self) -> Paragraph<'static>{
-        Paragraph::new(MODIFIED_INDICATOR)
-            .alignment(Alignment::Left)
-            .style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .bold()
-            )
+    /// the indentation each view line draws guides through. blank lines carry
+    /// guides between their neighbours, at the shallower of the two indents
+    fn guide_indents(&self, lines: &[&str]) -> Vec<usize>{
+        // scrolled right, the view no longer starts at the lines' indentation
+        if !self.show_whitespace || self.view_left > 0{
+            return vec![0; lines.len()];
+        }
+        let indents: Vec<Option<usize>> = lines.iter().map(|line| display::indent_cells(line, self.tab_width)).collect();
+        (0..lines.len())
+            .map(|index| match indents[index]{
+                Some(indent) => indent,
+                None => {
+                    let previous = indents[..index].iter().rev().find_map(|indent| *indent);
+                    let next = indents[index + 1..].iter().find_map(|indent| *indent);
+                    previous.unwrap_or(0).min(next.unwrap_or(0))
+                }
+            })
+            .collect()
     }
 
-    pub fn status_bar_file_name_widget(&self) -> Paragraph<'static>{
-        let file_name = match &self.document_file_name{
-            Some(file_name) => file_name.to_string(),
-            None => "None".to_string()
+    /// a line of document text as drawn on screen, with tabs expanded, control
+    /// characters made visible, and any whitespace markers, indent guides and
+    /// trailing whitespace highlight
+    fn document_line(&self, line: &str, start_column: usize, indent_width: usize, marks: LineMarks) -> Line<'static>{
+        let is_guide = |cell: usize| cell < marks.indent_cells && cell.is_multiple_of(indent_width);
+        let mut spans: Vec<Span> = Vec::new();
+        let mut push = |text: String, style: Style| match spans.last_mut(){
+            Some(span) if span.style == style => span.content.to_mut().push_str(&text),
+            _ => spans.push(Span::styled(text, style))
         };
-        Paragraph::new(file_name)
-            .alignment(Alignment::Left)
-            .style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .bold()
-                )
-    }
-
-    pub fn status_bar_cursor_position_widget(&self) -> Paragraph<'static>{
-        let position = match self.document_cursor_position{
-            Some(cursor_position) => {
-                format!(
-                    "{}:{}",
-                    cursor_position.y(),// + 1,
-                    cursor_position.x()// + 1
-                )
+
+        let mut cell = 0;
+        for (index, glyph) in display::glyphs(line, start_column, self.tab_width).into_iter().enumerate(){
+            let trailing = self.highlight_trailing_whitespace && marks.trailing_whitespace.is_some_and(|start| index >= start);
+            let mut style = match glyph.kind{
+                GlyphKind::Control => self.theme.style("ui.control_character"),
+                GlyphKind::Text | GlyphKind::Tab => Style::default()
+            };
+            if trailing{
+                style = style.patch(self.theme.style("ui.whitespace.trailing"));
             }
-            None => "None".to_string()
-        };
-        Paragraph::new(position)
-            .alignment(Alignment::Right)
-            .style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .bold()
-            )
+            let whitespace = glyph.kind == GlyphKind::Tab || glyph.text == " ";
+            if whitespace && (self.show_whitespace || marks.indent_cells > 0){
+                // whitespace is drawn a cell at a time, so guides can land inside tabs
+                for offset in 0..glyph.width{
+                    let marker = match (is_guide(cell + offset), offset, glyph.kind){
+                        (true, _, _) => INDENT_GUIDE,
+                        (false, 0, GlyphKind::Tab) if self.show_whitespace => TAB_MARKER,
+                        (false, 0, _) if self.show_whitespace => SPACE_MARKER,
+                        _ => ' '
+                    };
+                    let marker_style = if marker == ' '{style}else{style.patch(self.theme.style("ui.whitespace"))};
+                    push(marker.to_string(), marker_style);
+                }
+            }else{
+                push(glyph.text, style);
+            }
+            cell += glyph.width;
+        }
+        if marks.line_end && self.show_whitespace{
+            push(LINE_END_MARKER.to_string(), self.theme.style("ui.whitespace"));
+            cell += 1;
+        }
+        // guides continue through blank lines
+        while cell < marks.indent_cells{
+            let marker = if is_guide(cell){INDENT_GUIDE}else{' '};
+            push(marker.to_string(), self.theme.style("ui.whitespace"));
+            cell += 1;
+        }
+
+        Line::from(spans)
+    }
+
+    pub fn status_bar_widget(&self, segments: &[StatusSegment], mode: Mode, alignment: Alignment) -> Paragraph<'static>{
+        Paragraph::new(self.status_bar_line(segments, mode))
+            .alignment(alignment)
+            .style(self.theme.style("ui.statusbar"))
+    }
+
+    /// the status bar text for a group of segments. segments with nothing to
+    /// show, like the modified indicator on an unmodified document, are left out
+    fn status_bar_line(&self, segments: &[StatusSegment], mode: Mode) -> Line<'static>{
+        let spans: Vec<Span> = segments.iter()
+            .filter_map(|segment| {
+                let text = self.status_segment_text(*segment, mode)?;
+                // the mode badge is colored per mode, like ui.statusbar.mode.insert
+                let scope = match segment{
+                    StatusSegment::Mode => format!("ui.statusbar.mode.{}", keymap::section_name(mode)),
+                    _ => format!("ui.statusbar.{}", status_segment_scope(*segment))
+                };
+                Some(Span::styled(format!(" {text} "), self.theme.style(&scope)))
+            })
+            .collect();
+
+        Line::from(spans)
+    }
+
+    fn status_segment_text(&self, segment: StatusSegment, mode: Mode) -> Option<String>{
+        let cursor = self.document_cursor_position;
+        match segment{
+            StatusSegment::Mode => Some(keymap::section_name(mode).replace('_', " ").to_uppercase()),
+            StatusSegment::Modified => self.document_modified_status.then(|| MODIFIED_INDICATOR.to_string()),
+            StatusSegment::FileName => Some(self.document_file_name.clone().unwrap_or_else(|| "None".to_string())),
+            StatusSegment::FilePath => {
+                let path = self.document_path.as_deref()?;
+                let relative = std::env::current_dir().ok()
+                    .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf));
+                Some(relative.as_deref().unwrap_or(path).display().to_string())
+            }
+            StatusSegment::AbsolutePath => self.document_path.as_ref().map(|path| path.display().to_string()),
+            StatusSegment::Position => cursor.map(|cursor| format!("{}:{}", cursor.y() + 1, cursor.x() + 1)),
+            StatusSegment::Percent => {
+                let line = cursor?.y() + 1;
+                Some(format!("{}%", line * 100 / self.document_length.max(line)))
+            }
+            // the server holds documents as rust strings, so text is always utf-8
+            StatusSegment::Encoding => Some("utf-8".to_string()),
+            StatusSegment::LineEnding => self.line_ending.map(str::to_string),
+            StatusSegment::Language => self.document_path.as_deref().and_then(status::language).map(str::to_string),
+            StatusSegment::Cursors => (self.cursor_count > 1).then(|| format!("{} cursors", self.cursor_count)),
+            StatusSegment::GitBranch => self.git_branch.clone(),
+            StatusSegment::Diagnostics => {
+                let errors = self.annotations.count(AnnotationKind::DiagnosticError);
+                let warnings = self.annotations.count(AnnotationKind::DiagnosticWarning);
+                (errors + warnings > 0).then(|| format!("E:{errors} W:{warnings}"))
+            }
+            StatusSegment::PendingKeys => (!self.pending_keys.is_empty()).then(|| self.pending_keys.clone()),
+        }
     }
 
     pub fn util_bar_prompt_widget(&self, mode: Mode) -> Paragraph<'static>{
@@ -460,11 +1113,17 @@ impl UserInterface{
                 }else{
                     Paragraph::new(self.util_bar.text().to_string())
                         .scroll((0, self.util_bar.offset()))
-                        .style(Style::default().fg(Color::Red))
+                        .style(self.theme.style("ui.prompt.invalid"))
                 }
             }
             Mode::Command => {
-                Paragraph::new(self.util_bar.text().to_string()).scroll((0, self.util_bar.offset()))
+                if self.util_bar.text_is_valid || self.util_bar.text().is_empty(){
+                    Paragraph::new(self.util_bar.text().to_string()).scroll((0, self.util_bar.offset()))
+                }else{
+                    Paragraph::new(self.util_bar.text().to_string())
+                        .scroll((0, self.util_bar.offset()))
+                        .style(self.theme.style("ui.prompt.invalid"))
+                }
             }
             Mode::Warning(kind) => Paragraph::new(
                 match kind{
@@ -483,9 +1142,13 @@ impl UserInterface{
                 }
             )
                 .alignment(ratatui::prelude::Alignment::Center)
-                .style(Style::default().bg(Color::Red).bold())
+                .style(self.theme.style("ui.warning"))
             ,
-            _ => Paragraph::new("".to_string())
+            Mode::Insert | Mode::Normal | Mode::Visual | Mode::UndoHistory | Mode::Popup | Mode::FilePicker => match self.messages.current(){
+                Some(message) => Paragraph::new(format!(" {}", message.text))
+                    .style(self.theme.style(&format!("ui.message.{}", message.level.name()))),
+                None => Paragraph::new("".to_string())
+            }
         }
     }
 
@@ -508,32 +1171,186 @@ impl UserInterface{
         }
     }
 
+    /// the bindings in as many columns as it takes to show them all. if even
+    /// the popup's width isn't enough, the last cell counts the rest
+    pub fn key_help_widget(&self, entries: &[(String, String)], mode: Mode, area: Rect) -> Paragraph<'static>{
+        let keys_width = entries.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+        let action_width = entries.iter().map(|(_, action)| action.chars().count()).max().unwrap_or(0);
+        let column_width = keys_width + action_width + 3;
+        // inside the border
+        let max_rows = (area.height.saturating_sub(2) as usize).max(1);
+        let max_columns = (area.width.saturating_sub(2) as usize / column_width).max(1);
+        // columns are filled top to bottom, evened out so the last isn't left short
+        let (rows, shown) = if entries.len().div_ceil(max_rows) <= max_columns{
+            let columns = entries.len().div_ceil(max_rows).max(1);
+            (entries.len().div_ceil(columns).max(1), entries.len())
+        }else{
+            // leave the last cell for the count
+            (max_rows, max_columns * max_rows - 1)
+        };
+        let mut lines = vec![String::new(); rows];
+        for (index, (keys, action)) in entries[..shown].iter().enumerate(){
+            lines[index % rows].push_str(&format!(" {keys:>keys_width$}  {action:<action_width$}"));
+        }
+        if shown < entries.len(){
+            lines[rows - 1].push_str(&format!(" ... and {} more", entries.len() - shown));
+        }
+        let lines: Vec<Line> = lines.into_iter().map(|line| Line::from(line.trim_end().to_string())).collect();
+        let title = if self.pending_keys.is_empty(){
+            format!(" {} keys ", keymap::section_name(mode))
+        }else{
+            format!(" {} ... ", self.pending_keys)
+        };
+        Paragraph::new(lines)
+            .style(self.theme.style("ui.popup"))
+            .block(Block::default().borders(Borders::ALL).title(title))
+    }
+
+    pub fn undo_history_widget(&self, height: u16) -> Paragraph<'static>{
+        let history = &self.undo_history;
+        let lines: Vec<Line> = history.entries.iter().enumerate()
+            .map(|(index, entry)| {
+                let marker = if index == history.current{"*"}else{" "};
+                let text = format!(" {} {:>8}  {}", marker, format_age(entry.timestamp()), entry.description());
+                if index == history.selected{
+                    Line::from(Span::styled(text, self.theme.style("ui.popup.selected")))
+                }else{
+                    Line::from(text)
+                }
+            })
+            .collect();
+        // keep the selected entry in view, leaving room for the border
+        let offset = history.selected.saturating_sub(height.saturating_sub(3) as usize) as u16;
+        Paragraph::new(lines)
+            .scroll((offset, 0))
+            .style(self.theme.style("ui.popup"))
+            .block(Block::default().borders(Borders::ALL).title(" undo history "))
+    }
+
+    pub fn popup_widget(&self, title: &str, lines: &[String]) -> Paragraph<'static>{
+        let lines: Vec<Line> = lines.iter().map(|line| Line::from(format!(" {line}"))).collect();
+        Paragraph::new(lines)
+            .scroll((self.popup_offset, 0))
+            .style(self.theme.style("ui.popup"))
+            .block(Block::default().borders(Borders::ALL).title(format!(" {title} ")))
+    }
+
+    /// the picker's matches, scrolled to keep the selected one in view
+    pub fn file_picker_list_widget(&self, picker: &FilePicker, height: u16) -> Paragraph<'static>{
+        // room for the border
+        let rows = height.saturating_sub(2) as usize;
+        let offset = picker.selected().saturating_sub(rows.saturating_sub(1));
+        let lines: Vec<Line> = picker.matches().enumerate().skip(offset).take(rows)
+            .map(|(index, path)| {
+                let text = format!(" {path}");
+                if index == picker.selected(){
+                    Line::from(Span::styled(text, self.theme.style("ui.popup.selected")))
+                }else{
+                    Line::from(text)
+                }
+            })
+            .collect();
+        Paragraph::new(lines)
+            .style(self.theme.style("ui.popup"))
+            .block(Block::default().borders(Borders::ALL).title(format!(" {}/{} ", picker.match_count(), picker.file_count())))
+    }
+
+    /// the start of the selected file, with tabs and control characters drawn as in the document
+    pub fn file_picker_preview_widget(&self, picker: &FilePicker, height: u16) -> Paragraph<'static>{
+        let lines: Vec<Line> = picker.preview().iter().take(height.saturating_sub(2) as usize)
+            .map(|line| {
+                let text: String = display::glyphs(line, 0, self.tab_width).into_iter().map(|glyph| glyph.text).collect();
+                Line::from(format!(" {text}"))
+            })
+            .collect();
+        let title = picker.selected_path()
+            .map(|path| path.strip_prefix(picker.root()).map(Path::to_path_buf).unwrap_or(path))
+            .map(|path| format!(" {} ", path.display()))
+            .unwrap_or_default();
+        Paragraph::new(lines)
+            .style(self.theme.style("ui.popup"))
+            .block(Block::default().borders(Borders::ALL).title(title))
+    }
+
     // when in select mode, figure out how to change background color of text within cursor_head and cursor_anchor
-    pub fn render(&mut self, terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, mode: Mode) -> Result<(), Box<dyn Error>>{        
+    pub fn render(&mut self, terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, mode: Mode) -> std::io::Result<()>{        
         terminal.draw(
             |frame| {
 
+                // the base text colors, which every widget below draws over
+                frame.render_widget(Block::default().style(self.theme.style("ui.text")), frame.size());
+
                 // render widgets
                 frame.render_widget(self.line_number_widget(), self.line_number_rect);
+                frame.render_widget(self.sign_column_widget(), self.sign_column_rect);
                 frame.render_widget(self.document_widget(), self.document_rect);
-                frame.render_widget(self.status_bar_modified_indicator_widget(), self.status_bar_modified_indicator_rect);
-                frame.render_widget(self.status_bar_file_name_widget(), self.status_bar_file_name_rect);
-                frame.render_widget(self.status_bar_cursor_position_widget(), self.status_bar_cursor_position_rect);
+                frame.render_widget(self.status_bar_widget(&self.status_bar_layout.left, mode, Alignment::Left), self.status_bar_left_rect);
+                frame.render_widget(self.status_bar_widget(&self.status_bar_layout.center, mode, Alignment::Center), self.status_bar_center_rect);
+                frame.render_widget(self.status_bar_widget(&self.status_bar_layout.right, mode, Alignment::Right), self.status_bar_right_rect);
                 frame.render_widget(self.util_bar_prompt_widget(mode), self.util_bar_prompt_rect);
                 frame.render_widget(self.util_bar_widget(mode), self.util_bar_rect);
                 frame.render_widget(self.util_bar_alternate_prompt_widget(mode), self.util_bar_alternate_prompt_rect);
                 frame.render_widget(self.util_bar_alternate_widget(mode), self.util_bar_alternate_rect);
 
+                if let Mode::UndoHistory = mode{
+                    let popup_rect = centered_rect(60, 60, self.terminal_size);
+                    frame.render_widget(Clear, popup_rect);
+                    frame.render_widget(self.undo_history_widget(popup_rect.height), popup_rect);
+                }
+
+                if let (Mode::Popup, Some((title, lines))) = (mode, &self.popup){
+                    let popup_rect = centered_rect(80, 60, self.terminal_size);
+                    frame.render_widget(Clear, popup_rect);
+                    frame.render_widget(self.popup_widget(title, lines), popup_rect);
+                }
+
+                if let (Mode::FilePicker, Some(picker)) = (mode, &self.file_picker){
+                    let popup_rect = centered_rect(80, 70, self.terminal_size);
+                    let columns = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+                        .split(popup_rect);
+                    let rows = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Length(3), Constraint::Min(0)])
+                        .split(columns[0]);
+                    frame.render_widget(Clear, popup_rect);
+                    frame.render_widget(
+                        Paragraph::new(format!(" {}", picker.query()))
+                            .style(self.theme.style("ui.popup"))
+                            .block(Block::default().borders(Borders::ALL).title(" open ")),
+                        rows[0]
+                    );
+                    frame.render_widget(self.file_picker_list_widget(picker, rows[1].height), rows[1]);
+                    frame.render_widget(self.file_picker_preview_widget(picker, columns[1].height), columns[1]);
+                    self.file_picker_query_rect = rows[0];
+                }
+
+                if let Some(entries) = &self.key_help{
+                    let popup_rect = centered_rect(80, 60, self.terminal_size);
+                    frame.render_widget(Clear, popup_rect);
+                    frame.render_widget(self.key_help_widget(entries, mode, popup_rect), popup_rect);
+                }
+
                 // render cursor
                 match mode{
-                    Mode::Insert => {
-                        if let Some(pos) = self.client_cursor_position{
+                    Mode::Insert | Mode::Normal | Mode::Visual if self.soft_wrap => {
+                        let rows = self.wrapped_rows();
+                        if let Some((row, column)) = self.wrapped_cursor_cell(&rows){
                             frame.set_cursor(
-                                self.document_rect.x + pos.x() as u16, 
-                                self.document_rect.y + pos.y() as u16
+                                self.document_rect.x + column as u16,
+                                self.document_rect.y + (row - self.wrap_top(&rows)) as u16
                             )
                         }
                     }
+                    Mode::Insert | Mode::Normal | Mode::Visual => {
+                        // a cursor scrolled out of view isn't drawn
+                        if let (Some(pos), Some(cell)) = (self.client_cursor_position, self.cursor_cell()){
+                            if cell < self.document_rect.width as usize{
+                                frame.set_cursor(self.document_rect.x + cell as u16, self.document_rect.y + pos.y() as u16)
+                            }
+                        }
+                    }
                     Mode::Goto | Mode::Command => {
                         frame.set_cursor(
                             self.util_bar_rect.x + self.util_bar.cursor_position().saturating_sub(self.util_bar.offset()),
@@ -551,7 +1368,13 @@ impl UserInterface{
                             self.terminal_size.height
                         );
                     }
-                    Mode::Warning(_) => {}
+                    Mode::FilePicker => {
+                        // inside the query box's border, after its leading space
+                        let rect = self.file_picker_query_rect;
+                        let width = self.file_picker.as_ref().map(|picker| picker.query().width()).unwrap_or(0) as u16;
+                        frame.set_cursor((rect.x + 2 + width).min((rect.x + rect.width).saturating_sub(2)), rect.y + 1);
+                    }
+                    Mode::Warning(_) | Mode::UndoHistory | Mode::Popup => {}
                 }
             }
 
@@ -561,34 +1384,71 @@ impl UserInterface{
     }
 }
 
-//fn _centered_rect(percent_x: u16, percent_y: u16, r: ratatui::prelude::Rect) -> ratatui::prelude::Rect{
-//    let popup_layout = Layout::default()
-//        .direction(Direction::Vertical)
-//        .constraints(
-//            [
-//                Constraint::Percentage((100 - percent_y) / 2),
-//                Constraint::Percentage(percent_y),
-//                Constraint::Percentage((100 - percent_y) / 2),
-//            ]
-//            .as_ref(),
-//        )
-//        .split(r);
-//
-//    Layout::default()
-//        .direction(Direction::Horizontal)
-//        .constraints(
-//            [
-//                Constraint::Percentage((100 - percent_x) / 2),
-//                Constraint::Percentage(percent_x),
-//                Constraint::Percentage((100 - percent_x) / 2),
-//            ]
-//            .as_ref(),
-//        )
-//        .split(popup_layout[1])[1]
-//}
+fn centered_rect(percent_x: u16, percent_y: u16, r: ratatui::prelude::Rect) -> ratatui::prelude::Rect{
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(r);
 
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1])[1]
+}
 
 
+
+/// the theme scope under ui.statusbar a segment is styled with
+fn status_segment_scope(segment: StatusSegment) -> &'static str{
+    match segment{
+        StatusSegment::Mode => "mode",
+        StatusSegment::Modified => "modified",
+        StatusSegment::FileName | StatusSegment::FilePath | StatusSegment::AbsolutePath => "path",
+        StatusSegment::Position | StatusSegment::Percent => "position",
+        StatusSegment::Encoding | StatusSegment::LineEnding | StatusSegment::Language => "file_info",
+        StatusSegment::Cursors => "cursors",
+        StatusSegment::GitBranch => "git_branch",
+        StatusSegment::Diagnostics => "diagnostics",
+        StatusSegment::PendingKeys => "pending_keys",
+    }
+}
+
+/// what to mark on a drawn document line besides its text
+struct LineMarks{
+    /// graphemes from this index on are trailing whitespace
+    trailing_whitespace: Option<usize>,
+    /// cells of indentation to draw guides through
+    indent_cells: usize,
+    /// the line ends on this row, rather than continuing on a wrapped row
+    line_end: bool,
+}
+
+/// how long ago a unix timestamp was, like "42s ago" or "3h ago"
+fn format_age(timestamp: u64) -> String{
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+    let age = now.saturating_sub(timestamp);
+    match age{
+        0..=59 => format!("{age}s ago"),
+        60..=3599 => format!("{}m ago", age / 60),
+        3600..=86399 => format!("{}h ago", age / 3600),
+        _ => format!("{}d ago", age / 86400)
+    }
+}
+
 fn count_digits(mut n: usize) -> u16{
     if n == 0{
         return 1;
@@ -0,0 +1,190 @@
+use crate::display;
+use unicode_segmentation::UnicodeSegmentation;
+
+
+
+/// one screen row of a soft wrapped view. `start` and `end` are grapheme
+/// indices into line `line` of the view
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VisualRow{
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+impl VisualRow{
+    /// true for every row of a line after its first
+    pub fn is_continuation(&self) -> bool{
+        self.start > 0
+    }
+}
+
+/// splits each line of `text` into rows no wider than `width` cells. with
+/// `at_words`, rows break after the last whitespace that fits where possible.
+/// tab stops restart at the beginning of every row. `cursor` is the (grapheme,
+/// view line) position of the cursor, if it is in view
+pub fn wrap_text(text: &str, width: usize, at_words: bool, tab_width: usize, cursor: Option<(usize, usize)>) -> Vec<VisualRow>{
+    let mut rows = Vec::new();
+    for (line, line_text) in text.lines().enumerate(){
+        let cursor_x = cursor.filter(|(_, y)| *y == line).map(|(x, _)| x);
+        for (start, end) in wrap_line(line_text, width, at_words, tab_width, cursor_x){
+            rows.push(VisualRow{line, start, end});
+        }
+    }
+
+    rows
+}
+
+fn wrap_line(line: &str, width: usize, at_words: bool, tab_width: usize, cursor_x: Option<usize>) -> Vec<(usize, usize)>{
+    let graphemes: Vec<&str> = line.graphemes(true).collect();
+    if width == 0{
+        return vec![(0, graphemes.len())];
+    }
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    // the grapheme index just after the last whitespace in the current row
+    let mut last_break = None;
+    for (index, grapheme) in graphemes.iter().enumerate(){
+        // a grapheme wider than the whole row still gets a row of its own
+        while used + display::glyph(grapheme, used, tab_width).width > width && index > start{
+            let end = match last_break{
+                Some(end) if at_words && end > start => end,
+                _ => index
+            };
+            rows.push((start, end));
+            used = display::glyphs(&graphemes[end..index].concat(), 0, tab_width).iter().map(|glyph| glyph.width).sum();
+            start = end;
+            last_break = None;
+        }
+        used += display::glyph(grapheme, used, tab_width).width;
+        if grapheme.chars().all(char::is_whitespace){
+            last_break = Some(index + 1);
+        }
+    }
+    rows.push((start, graphemes.len()));
+    // the cursor after the last grapheme of a full row needs a cell of its own.
+    // other lines don't get the row, so they take no more rows than their text
+    if used >= width && cursor_x.is_some_and(|x| x >= graphemes.len()){
+        rows.push((graphemes.len(), graphemes.len()));
+    }
+
+    rows
+}
+
+/// the text of a row, given the view line it belongs to
+pub fn row_text(line: &str, row: &VisualRow) -> String{
+    line.graphemes(true).skip(row.start).take(row.end - row.start).collect()
+}
+
+/// the row and cell column showing grapheme `x` of view line `y`
+pub fn cursor_cell(rows: &[VisualRow], lines: &[&str], x: usize, y: usize, tab_width: usize) -> Option<(usize, usize)>{
+    let line = lines.get(y).copied().unwrap_or("");
+    // the cursor may sit past the last grapheme, which belongs to the line's last row
+    let index = rows.iter().enumerate()
+        .filter(|(_, row)| row.line == y)
+        .find(|(_, row)| x < row.end)
+        .or_else(|| rows.iter().enumerate().rfind(|(_, row)| row.line == y))
+        .map(|(index, _)| index)?;
+    let row = rows[index];
+    let column = display::cell_of(&row_text(line, &row), x.saturating_sub(row.start), 0, tab_width);
+
+    Some((index, column))
+}
+
+/// the (grapheme, view line) position shown at a cell column of a row. columns
+/// past the row's end give its last position, which for a continued line is
+/// the last grapheme rather than the start of the next row
+pub fn position_at(rows: &[VisualRow], lines: &[&str], index: usize, column: usize, tab_width: usize) -> Option<(usize, usize)>{
+    let row = rows.get(index)?;
+    let line = lines.get(row.line).copied().unwrap_or("");
+    let continued = rows.get(index + 1).is_some_and(|next| next.line == row.line);
+    let offset = display::column_at(&row_text(line, row), column, 0, tab_width);
+    let x = if offset < row.end - row.start{
+        row.start + offset
+    }else if continued && row.end > row.start{
+        row.end - 1
+    }else{
+        row.end
+    };
+
+    Some((x, row.line))
+}
+
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn rows(line: &str, width: usize, at_words: bool) -> Vec<(usize, usize)>{
+        wrap_line(line, width, at_words, 4, None)
+    }
+
+    /// every cursor position of every line maps to a cell and back
+    fn check_round_trip(text: &str, width: usize, at_words: bool){
+        let lines: Vec<&str> = text.lines().collect();
+        for (y, line) in lines.iter().enumerate(){
+            for x in 0..=line.graphemes(true).count(){
+                let rows = wrap_text(text, width, at_words, 4, Some((x, y)));
+                let (index, column) = cursor_cell(&rows, &lines, x, y, 4).unwrap();
+                assert!(column < width, "{text:?} at {x}: column {column} past width {width}");
+                assert_eq!(position_at(&rows, &lines, index, column, 4), Some((x, y)), "{text:?} at {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn wide_graphemes_move_to_the_next_row_whole(){
+        assert_eq!(rows("ab中", 3, false), vec![(0, 2), (2, 3)]);
+        assert_eq!(rows("a👍b", 2, false), vec![(0, 1), (1, 2), (2, 3)]);
+        // wider than the row, so it gets one to itself rather than looping
+        assert_eq!(rows("中中", 1, false), vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base(){
+        assert_eq!(rows("e\u{301}e\u{301}e\u{301}", 2, false), vec![(0, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn tab_stops_restart_on_each_row(){
+        assert_eq!(rows("a\tb", 6, false), vec![(0, 3)]);
+        // the tab would end at cell 8, so it starts the next row 4 cells wide
+        assert_eq!(rows("abcde\tf", 6, false), vec![(0, 5), (5, 7)]);
+        assert_eq!(rows("\tab", 4, false), vec![(0, 1), (1, 3)]);
+    }
+
+    #[test]
+    fn at_words_breaks_after_whitespace(){
+        assert_eq!(rows("one two three", 6, true), vec![(0, 4), (4, 8), (8, 13)]);
+        assert_eq!(rows("one two three", 6, false), vec![(0, 6), (6, 12), (12, 13)]);
+        // a word longer than the row is broken anyway
+        assert_eq!(rows("abcdefgh ij", 4, true), vec![(0, 4), (4, 8), (8, 11)]);
+    }
+
+    #[test]
+    fn full_rows_get_an_end_row_only_for_the_cursor(){
+        assert_eq!(wrap_line("abcd", 4, false, 4, None), vec![(0, 4)]);
+        assert_eq!(wrap_line("abcd", 4, false, 4, Some(2)), vec![(0, 4)]);
+        assert_eq!(wrap_line("abcd", 4, false, 4, Some(4)), vec![(0, 4), (4, 4)]);
+        let rows = wrap_text("abcd\nefgh", 4, false, 4, Some((4, 1)));
+        assert_eq!(rows.iter().map(|row| (row.line, row.start, row.end)).collect::<Vec<_>>(), vec![(0, 0, 4), (1, 0, 4), (1, 4, 4)]);
+    }
+
+    #[test]
+    fn cursor_cells_and_positions_round_trip(){
+        check_round_trip("hello world\nabcd", 4, false);
+        check_round_trip("hello world\nabcd", 4, true);
+        check_round_trip("a中b👍c\n\te\u{301}\tx\n", 3, false);
+        check_round_trip("中中中\n", 4, true);
+    }
+
+    #[test]
+    fn positions_past_a_continued_row_stay_on_it(){
+        let lines = ["abcdef"];
+        let rows = wrap_text("abcdef", 4, false, 4, None);
+        assert_eq!(position_at(&rows, &lines, 0, 10, 4), Some((3, 0)));
+        assert_eq!(position_at(&rows, &lines, 1, 10, 4), Some((6, 0)));
+    }
+}